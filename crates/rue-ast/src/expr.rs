@@ -1,12 +1,14 @@
 mod binary_expr;
 mod call_expr;
 mod if_expr;
+mod list_expr;
 mod literal_expr;
 mod prefix_expr;
 
 pub use binary_expr::*;
 pub use call_expr::*;
 pub use if_expr::*;
+pub use list_expr::*;
 pub use literal_expr::*;
 pub use prefix_expr::*;
 
@@ -18,4 +20,5 @@ ast_enum! { Expr,
     Prefix(PrefixExpr),
     Call(CallExpr),
     If(IfExpr),
+    List(ListExpr),
 }
@@ -0,0 +1,11 @@
+use rowan::ast::AstNode;
+
+use crate::{ast_node, Expr};
+
+ast_node!(ListExpr);
+
+impl ListExpr {
+    pub fn items(&self) -> Vec<Expr> {
+        self.0.children().filter_map(Expr::cast).collect()
+    }
+}
@@ -1,17 +1,30 @@
 use rowan::ast::AstNode;
 use rue_syntax::{SyntaxElement, SyntaxKind, SyntaxToken};
 
+mod attribute;
 mod fn_param;
 mod fn_param_list;
 
+pub use attribute::*;
 pub use fn_param::*;
 pub use fn_param_list::*;
 
-use crate::{ast_node, Block};
+use crate::{ast_node, Block, TypeRef};
 
 ast_node!(FnItem);
 
 impl FnItem {
+    pub fn attributes(&self) -> Vec<Attribute> {
+        self.0.children().filter_map(Attribute::cast).collect()
+    }
+
+    pub fn inline(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Inline)
+    }
+
     pub fn name(&self) -> Option<SyntaxToken> {
         self.0
             .children_with_tokens()
@@ -23,12 +36,8 @@ impl FnItem {
         self.0.children().find_map(FnParamList::cast)
     }
 
-    pub fn return_type(&self) -> Option<SyntaxToken> {
-        self.0
-            .children_with_tokens()
-            .filter_map(SyntaxElement::into_token)
-            .filter(|token| token.kind() == SyntaxKind::Ident)
-            .nth(1)
+    pub fn return_type(&self) -> Option<TypeRef> {
+        TypeRef::find(&self.0, 1)
     }
 
     pub fn block(&self) -> Option<Block> {
@@ -0,0 +1,14 @@
+use rue_syntax::{SyntaxElement, SyntaxKind, SyntaxToken};
+
+use crate::ast_node;
+
+ast_node!(Attribute);
+
+impl Attribute {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+}
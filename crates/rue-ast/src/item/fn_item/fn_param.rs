@@ -1,22 +1,25 @@
 use rue_syntax::{SyntaxElement, SyntaxKind, SyntaxToken};
 
-use crate::ast_node;
+use crate::{ast_node, TypeRef};
 
 ast_node!(FnParam);
 
 impl FnParam {
-    pub fn name(&self) -> Option<SyntaxToken> {
+    pub fn curry(&self) -> Option<SyntaxToken> {
         self.0
             .children_with_tokens()
             .filter_map(SyntaxElement::into_token)
-            .find(|token| token.kind() == SyntaxKind::Ident)
+            .find(|token| token.kind() == SyntaxKind::Curry)
     }
 
-    pub fn ty(&self) -> Option<SyntaxToken> {
+    pub fn name(&self) -> Option<SyntaxToken> {
         self.0
             .children_with_tokens()
             .filter_map(SyntaxElement::into_token)
-            .filter(|token| token.kind() == SyntaxKind::Ident)
-            .nth(1)
+            .find(|token| token.kind() == SyntaxKind::Ident)
+    }
+
+    pub fn ty(&self) -> Option<TypeRef> {
+        TypeRef::find(&self.0, 1)
     }
 }
@@ -3,12 +3,14 @@ mod expr;
 mod item;
 mod program;
 mod stmt;
+mod ty;
 
 pub use block::*;
 pub use expr::*;
 pub use item::*;
 pub use program::*;
 pub use stmt::*;
+pub use ty::*;
 
 #[macro_export]
 macro_rules! ast_node {
@@ -1,7 +1,7 @@
 use rowan::ast::AstNode;
 use rue_syntax::{SyntaxElement, SyntaxKind, SyntaxToken};
 
-use crate::{ast_node, Expr};
+use crate::{ast_node, Expr, TypeRef};
 
 ast_node!(LetStmt);
 
@@ -13,12 +13,8 @@ impl LetStmt {
             .find(|token| token.kind() == SyntaxKind::Ident)
     }
 
-    pub fn ty(&self) -> Option<SyntaxToken> {
-        self.0
-            .children_with_tokens()
-            .filter_map(SyntaxElement::into_token)
-            .filter(|token| token.kind() == SyntaxKind::Ident)
-            .nth(1)
+    pub fn ty(&self) -> Option<TypeRef> {
+        TypeRef::find(&self.0, 1)
     }
 
     pub fn value(&self) -> Option<Expr> {
@@ -0,0 +1,52 @@
+use rowan::{ast::AstNode, NodeOrToken};
+use rue_syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+
+use crate::ast_node;
+
+ast_node!(ListType);
+
+impl ListType {
+    pub fn item_type(&self) -> Option<TypeRef> {
+        TypeRef::find(&self.0, 0)
+    }
+}
+
+/// A type as it's written in the source, which is either a name or a list type such as `[Int]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeRef {
+    Named(SyntaxToken),
+    List(ListType),
+}
+
+impl TypeRef {
+    /// Finds the type in a node, after skipping the given number of identifiers before it.
+    pub(crate) fn find(node: &SyntaxNode, skip: usize) -> Option<Self> {
+        let mut skipped = 0;
+
+        for child in node.children_with_tokens() {
+            match child {
+                NodeOrToken::Token(token) if token.kind() == SyntaxKind::Ident => {
+                    if skipped == skip {
+                        return Some(Self::Named(token));
+                    }
+                    skipped += 1;
+                }
+                NodeOrToken::Node(node) if skipped == skip => {
+                    if let Some(list_type) = ListType::cast(node) {
+                        return Some(Self::List(list_type));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    pub fn syntax(&self) -> SyntaxElement {
+        match self {
+            Self::Named(token) => NodeOrToken::Token(token.clone()),
+            Self::List(list_type) => NodeOrToken::Node(list_type.syntax().clone()),
+        }
+    }
+}
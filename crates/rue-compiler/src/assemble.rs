@@ -0,0 +1,199 @@
+use std::{iter::Peekable, str::CharIndices};
+
+use clvmr::{allocator::NodePtr, Allocator};
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+use crate::disassemble::keyword;
+
+/// Parses a value written in CLVM text format, such as `(a (q . 42) 1)` or `(1 "two" 0x03)`.
+///
+/// Keywords are accepted anywhere an atom is, and are replaced by their opcode.
+pub fn assemble(allocator: &mut Allocator, text: &str) -> Result<NodePtr, String> {
+    let mut parser = Parser {
+        text,
+        chars: text.char_indices().peekable(),
+        allocator,
+    };
+
+    let node = parser.parse_node()?;
+    parser.skip_whitespace();
+
+    match parser.chars.next() {
+        Some((index, c)) => Err(format!("unexpected `{c}` at {index}")),
+        None => Ok(node),
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    allocator: &'a mut Allocator,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_node(&mut self) -> Result<NodePtr, String> {
+        self.skip_whitespace();
+
+        match self.chars.peek().copied() {
+            None => Err("unexpected end of input".to_string()),
+            Some((_, '(')) => {
+                self.chars.next();
+                self.parse_list()
+            }
+            Some((index, c @ (')' | '.'))) => Err(format!("unexpected `{c}` at {index}")),
+            Some((_, quote @ ('"' | '\''))) => {
+                self.chars.next();
+                self.parse_string(quote)
+            }
+            Some(_) => {
+                let token = self.parse_token();
+                self.parse_atom(token)
+            }
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<NodePtr, String> {
+        let mut items = Vec::new();
+        let mut tail = self.allocator.null();
+
+        loop {
+            self.skip_whitespace();
+
+            match self.chars.peek().copied() {
+                None => return Err("unterminated list".to_string()),
+                Some((_, ')')) => {
+                    self.chars.next();
+                    break;
+                }
+                Some((index, '.')) if !items.is_empty() => {
+                    self.chars.next();
+                    tail = self.parse_node()?;
+                    self.skip_whitespace();
+                    if self.chars.next().map(|(_, c)| c) != Some(')') {
+                        return Err(format!("expected `)` after the tail at {index}"));
+                    }
+                    break;
+                }
+                Some(_) => items.push(self.parse_node()?),
+            }
+        }
+
+        for item in items.into_iter().rev() {
+            tail = self
+                .allocator
+                .new_pair(item, tail)
+                .map_err(|error| error.1)?;
+        }
+
+        Ok(tail)
+    }
+
+    fn parse_string(&mut self, quote: char) -> Result<NodePtr, String> {
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next() {
+                None => return Err("unterminated string".to_string()),
+                Some((_, c)) if c == quote => break,
+                Some((_, c)) => value.push(c),
+            }
+        }
+
+        self.allocator
+            .new_atom(value.as_bytes())
+            .map_err(|error| error.1)
+    }
+
+    fn parse_token(&mut self) -> &'a str {
+        let start = self.chars.peek().unwrap().0;
+        let mut end = self.text.len();
+
+        while let Some((index, c)) = self.chars.peek().copied() {
+            if c.is_whitespace() || c == '(' || c == ')' {
+                end = index;
+                break;
+            }
+            self.chars.next();
+        }
+
+        &self.text[start..end]
+    }
+
+    fn parse_atom(&mut self, token: &str) -> Result<NodePtr, String> {
+        let bytes = if let Some(hex) = token.strip_prefix("0x") {
+            parse_hex(hex).ok_or_else(|| format!("invalid hex atom `{token}`"))?
+        } else if let Ok(value) = token.parse::<BigInt>() {
+            if value.is_zero() {
+                Vec::new()
+            } else {
+                value.to_signed_bytes_be()
+            }
+        } else if let Some(opcode) = (0..=u8::MAX).find(|opcode| keyword(&[*opcode]) == Some(token))
+        {
+            vec![opcode]
+        } else {
+            return Err(format!("unknown keyword `{token}`"));
+        };
+
+        self.allocator.new_atom(&bytes).map_err(|error| error.1)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+}
+
+/// Decodes a string of hex digits, which must have an even length.
+pub fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disassemble;
+
+    use super::*;
+
+    fn check(text: &str, expected: &str) {
+        let mut allocator = Allocator::new();
+        let node = assemble(&mut allocator, text).unwrap();
+        assert_eq!(disassemble(&allocator, node), expected);
+    }
+
+    #[test]
+    fn assemble_atoms() {
+        check("()", "()");
+        check("0", "()");
+        check("42", "42");
+        check("-1", "-1");
+        check("\"hello\"", "\"hello\"");
+        check("'hello'", "\"hello\"");
+        check("0x00000000ff", "0x00000000ff");
+    }
+
+    #[test]
+    fn assemble_lists() {
+        check("(100 2 3)", "(100 2 3)");
+        check("  ( 100 ( 200 . 300 ) )  ", "(100 (200 . 300))");
+        check("(a (q . (+ 2 5)) 1)", "(a (q + 2 5) 1)");
+    }
+
+    #[test]
+    fn assemble_errors() {
+        let mut allocator = Allocator::new();
+        assert!(assemble(&mut allocator, "(1 2").is_err());
+        assert!(assemble(&mut allocator, "(1 . 2 3)").is_err());
+        assert!(assemble(&mut allocator, "(. 1)").is_err());
+        assert!(assemble(&mut allocator, "1 2").is_err());
+        assert!(assemble(&mut allocator, "0xabc").is_err());
+        assert!(assemble(&mut allocator, "unknown").is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use clvmr::{allocator::NodePtr, reduction::EvalErr, Allocator};
+
+/// Curries values into a program, so that they are prepended to its environment.
+///
+/// The result is `(a (q . PROGRAM) (c (q . ARG1) (c (q . ARG2) 1)))`, and its tree hash
+/// can be computed from the hash of the program alone with
+/// [`curry_tree_hash`](crate::curry_tree_hash).
+pub fn curry(
+    allocator: &mut Allocator,
+    program: NodePtr,
+    args: &[NodePtr],
+) -> Result<NodePtr, EvalErr> {
+    let op_q = allocator.one();
+    let op_a = allocator.new_atom(&[2])?;
+    let op_c = allocator.new_atom(&[4])?;
+
+    let mut environment = allocator.one();
+    for arg in args.iter().rev() {
+        let quoted = allocator.new_pair(op_q, *arg)?;
+        environment = new_list(allocator, &[op_c, quoted, environment])?;
+    }
+
+    let quoted = allocator.new_pair(op_q, program)?;
+    new_list(allocator, &[op_a, quoted, environment])
+}
+
+fn new_list(allocator: &mut Allocator, items: &[NodePtr]) -> Result<NodePtr, EvalErr> {
+    let mut list = allocator.null();
+    for item in items.iter().rev() {
+        list = allocator.new_pair(*item, list)?;
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assemble, curry_tree_hash, disassemble, run, tree_hash, CurriedValue, DEFAULT_MAX_COST,
+    };
+
+    use super::*;
+
+    #[test]
+    fn curry_values() {
+        let mut allocator = Allocator::new();
+        let program = assemble(&mut allocator, "(- 2 5)").unwrap();
+        let arg = assemble(&mut allocator, "50").unwrap();
+        let curried = curry(&mut allocator, program, &[arg]).unwrap();
+
+        assert_eq!(
+            disassemble(&allocator, curried),
+            "(a (q - 2 5) (c (q . 50) 1))"
+        );
+
+        let solution = assemble(&mut allocator, "(8)").unwrap();
+        let (value, _) = run(&mut allocator, curried, solution, DEFAULT_MAX_COST).unwrap();
+        assert_eq!(disassemble(&allocator, value), "42");
+
+        assert_eq!(
+            tree_hash(&allocator, curried),
+            curry_tree_hash(
+                &tree_hash(&allocator, program),
+                &[CurriedValue::Int(50.into())]
+            )
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use clvmr::{
+    allocator::{NodePtr, SExp},
+    Allocator,
+};
+use num_bigint::BigInt;
+
+/// Renders a program in the usual CLVM text format, such as `(a (q . 42) 1)`.
+///
+/// Atoms in operator position are written as keywords. Other atoms are written as strings
+/// if they are printable and longer than two bytes, as integers if they are short and
+/// canonically encoded, and as hex otherwise.
+pub fn disassemble(allocator: &Allocator, node: NodePtr) -> String {
+    let mut output = String::new();
+    write_node(allocator, node, true, &mut output);
+    output
+}
+
+/// Renders a value in CLVM text format, without treating any atoms as keywords.
+/// This is more readable than [`disassemble`] for data such as solutions and results.
+pub fn disassemble_value(allocator: &Allocator, node: NodePtr) -> String {
+    let mut output = String::new();
+    write_node(allocator, node, false, &mut output);
+    output
+}
+
+fn write_node(allocator: &Allocator, node: NodePtr, keywords: bool, output: &mut String) {
+    let SExp::Pair(first, rest) = allocator.sexp(node) else {
+        output.push_str(&atom_text(allocator.atom(node)));
+        return;
+    };
+
+    output.push('(');
+    write_items(allocator, first, rest, keywords, output);
+    output.push(')');
+}
+
+/// Writes the items of a list without its parentheses.
+fn write_items(
+    allocator: &Allocator,
+    first: NodePtr,
+    mut rest: NodePtr,
+    keywords: bool,
+    output: &mut String,
+) {
+    match allocator.sexp(first) {
+        SExp::Atom => {
+            let atom = allocator.atom(first);
+            match keyword(atom).filter(|_| keywords) {
+                // A quoted list continues the quote, like `(q + 2 5)`, so its operator is
+                // written as a keyword too.
+                Some("q") => {
+                    output.push('q');
+                    if let SExp::Pair(quoted, next) = allocator.sexp(rest) {
+                        output.push(' ');
+                        write_items(allocator, quoted, next, keywords, output);
+                        return;
+                    }
+                }
+                Some(keyword) => output.push_str(keyword),
+                None => output.push_str(&atom_text(atom)),
+            }
+        }
+        SExp::Pair(..) => write_node(allocator, first, keywords, output),
+    }
+
+    while let SExp::Pair(item, next) = allocator.sexp(rest) {
+        output.push(' ');
+        write_node(allocator, item, keywords, output);
+        rest = next;
+    }
+
+    if !allocator.atom(rest).is_empty() {
+        output.push_str(" . ");
+        output.push_str(&atom_text(allocator.atom(rest)));
+    }
+}
+
+fn atom_text(atom: &[u8]) -> String {
+    if atom.is_empty() {
+        return "()".to_string();
+    }
+
+    if atom.len() > 2
+        && atom
+            .iter()
+            .all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+        && !atom.contains(&b'"')
+    {
+        return format!("\"{}\"", String::from_utf8_lossy(atom));
+    }
+
+    if atom.len() <= 4 && is_canonical(atom) {
+        return BigInt::from_signed_bytes_be(atom).to_string();
+    }
+
+    format!("0x{}", hex(atom))
+}
+
+fn is_canonical(atom: &[u8]) -> bool {
+    match atom {
+        [0x00] => false,
+        [0x00, next, ..] => next & 0x80 != 0,
+        [0xff, next, ..] => next & 0x80 == 0,
+        _ => true,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub(crate) fn keyword(atom: &[u8]) -> Option<&'static str> {
+    let [opcode] = atom else {
+        return None;
+    };
+
+    Some(match opcode {
+        1 => "q",
+        2 => "a",
+        3 => "i",
+        4 => "c",
+        5 => "f",
+        6 => "r",
+        7 => "l",
+        8 => "x",
+        9 => "=",
+        10 => ">s",
+        11 => "sha256",
+        12 => "substr",
+        13 => "strlen",
+        14 => "concat",
+        16 => "+",
+        17 => "-",
+        18 => "*",
+        19 => "/",
+        20 => "divmod",
+        21 => ">",
+        22 => "ash",
+        23 => "lsh",
+        24 => "logand",
+        25 => "logior",
+        26 => "logxor",
+        27 => "lognot",
+        29 => "point_add",
+        30 => "pubkey_for_exp",
+        32 => "not",
+        33 => "any",
+        34 => "all",
+        36 => "softfork",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clvmr::serde::node_from_bytes;
+
+    use super::*;
+
+    fn check(hex: &str, expected: &str) {
+        let mut allocator = Allocator::new();
+        let bytes = crate::parse_hex(hex).unwrap();
+        let node = node_from_bytes(&mut allocator, &bytes).unwrap();
+        assert_eq!(disassemble(&allocator, node), expected);
+    }
+
+    #[test]
+    fn disassemble_atoms() {
+        check("80", "()");
+        check("05", "5");
+        check("8200ff", "255");
+        check("81ff", "-1");
+        check("8568656c6c6f", "\"hello\"");
+        check("83616263", "\"abc\"");
+        check("826162", "24930");
+        check("8500000000ff", "0x00000000ff");
+    }
+
+    #[test]
+    fn disassemble_programs() {
+        check("ff02ffff01ff10ff02ff0580ff0180", "(a (q + 2 5) 1)");
+        check("ff01ff0105", "(q q . 5)");
+        check("ff0105", "(q . 5)");
+        check("ff0183616263", "(q . \"abc\")");
+        check("ff0aff02ff0580", "(>s 2 5)");
+        check("ff04ff0203", "(c 2 . 3)");
+        check("ffff0580ff0680", "((f) 6)");
+    }
+
+    #[test]
+    fn disassemble_values() {
+        let mut allocator = Allocator::new();
+        let node =
+            node_from_bytes(&mut allocator, &crate::parse_hex("ff0aff0580").unwrap()).unwrap();
+        assert_eq!(disassemble_value(&allocator, node), "(10 5)");
+    }
+}
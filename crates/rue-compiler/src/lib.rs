@@ -1,207 +1,873 @@
-use clvmr::{allocator::NodePtr, reduction::EvalErr, serde::node_to_bytes, Allocator};
-use num_traits::{One, Zero};
-use rue_lir::Lir;
+use std::{collections::HashMap, io};
 
+use clvmr::{
+    allocator::NodePtr,
+    reduction::EvalErr,
+    serde::{node_to_bytes, node_to_bytes_backrefs},
+    Allocator,
+};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use rue_lir::{CompileError, CompileErrorKind, Lir};
+
+mod assemble;
+mod curry;
+mod disassemble;
+mod runtime;
+mod select;
+mod source_map;
+mod tree_hash;
+
+pub use assemble::*;
+pub use curry::*;
+pub use disassemble::*;
+pub use runtime::*;
+pub use source_map::*;
+pub use tree_hash::*;
+
+use select::{select, Clvm};
+
+/// Compiles `Lir` into CLVM.
+///
+/// Identical atoms and pairs are only allocated once, so repeated subtrees such as
+/// duplicated function bodies are shared. This lets the back-reference serializer
+/// replace them with a reference to the first occurrence.
 pub struct Compiler {
     allocator: Allocator,
     nil: NodePtr,
-    one: NodePtr,
     op_q: NodePtr,
-    op_a: NodePtr,
-    op_i: NodePtr,
-    op_c: NodePtr,
-    op_eq: NodePtr,
-    op_add: NodePtr,
-    op_sub: NodePtr,
-    op_mul: NodePtr,
-    op_div: NodePtr,
-    op_gt: NodePtr,
-    op_not: NodePtr,
-    op_any: NodePtr,
+    atoms: HashMap<Vec<u8>, NodePtr>,
+    pairs: HashMap<(NodePtr, NodePtr), NodePtr>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Compiler {
     pub fn new() -> Self {
-        let mut allocator = Allocator::new();
-
+        let allocator = Allocator::new();
         let nil = allocator.null();
-        let one = allocator.one();
         let op_q = allocator.one();
-        let op_a = allocator.new_atom(&[2]).unwrap();
-        let op_i = allocator.new_atom(&[3]).unwrap();
-        let op_c = allocator.new_atom(&[4]).unwrap();
-        let op_eq = allocator.new_atom(&[9]).unwrap();
-        let op_add = allocator.new_atom(&[16]).unwrap();
-        let op_sub = allocator.new_atom(&[17]).unwrap();
-        let op_mul = allocator.new_atom(&[18]).unwrap();
-        let op_div = allocator.new_atom(&[19]).unwrap();
-        let op_gt = allocator.new_atom(&[21]).unwrap();
-        let op_not = allocator.new_atom(&[32]).unwrap();
-        let op_any = allocator.new_atom(&[33]).unwrap();
 
         Self {
             allocator,
             nil,
-            one,
             op_q,
-            op_a,
-            op_i,
-            op_c,
-            op_eq,
-            op_add,
-            op_sub,
-            op_mul,
-            op_div,
-            op_gt,
-            op_not,
-            op_any,
-        }
-    }
-
-    pub fn compile_to_bytes(&mut self, value: Lir) -> Vec<u8> {
-        let value = self.optimize(value);
-        let ptr = self.compile(value);
-        node_to_bytes(&self.allocator, ptr).unwrap()
-    }
-
-    fn optimize(&mut self, value: Lir) -> Lir {
-        match &value {
-            Lir::Int(_) => value,
-            Lir::String(_) => value,
-            Lir::Add(_) => value,
-            Lir::Sub(_) => value,
-            Lir::Mul(_) => value,
-            Lir::Div(_) => value,
-            Lir::Lt(_, _) => value,
-            Lir::Gt(_, _) => value,
-            Lir::Path(_) => value,
-            Lir::Environment { .. } => value,
-            Lir::Quote(_) => value,
-            Lir::If { .. } => value,
-        }
-    }
-
-    fn compile(&mut self, value: Lir) -> NodePtr {
-        match value {
-            Lir::Int(value) => {
-                if value.is_zero() {
-                    self.nil
-                } else if value.is_one() {
-                    self.quote(self.op_q).unwrap()
-                } else {
-                    let value = self.allocator.new_number(value).unwrap();
-                    self.quote(value).unwrap()
-                }
+            atoms: HashMap::from([(Vec::new(), nil), (vec![1], op_q)]),
+            pairs: HashMap::new(),
+        }
+    }
+
+    pub fn compile_to_bytes(&mut self, value: Lir) -> Result<Vec<u8>, CompileError> {
+        let ptr = self.compile(value)?;
+        self.serialize(ptr)
+    }
+
+    /// Serializes the program with back-references to repeated subtrees, which is
+    /// usually smaller but can only be read by deserializers that support them.
+    pub fn compile_to_compact_bytes(&mut self, value: Lir) -> Result<Vec<u8>, CompileError> {
+        let ptr = self.compile(value)?;
+        self.serialize_compact(ptr)
+    }
+
+    pub fn compile_to_text(&mut self, value: Lir) -> Result<String, CompileError> {
+        let ptr = self.compile(value)?;
+        Ok(disassemble(&self.allocator, ptr))
+    }
+
+    /// Computes the tree hash of the program, which identifies it as a puzzle.
+    pub fn compile_to_tree_hash(&mut self, value: Lir) -> Result<TreeHash, CompileError> {
+        let ptr = self.compile(value)?;
+        Ok(self.tree_hash(ptr))
+    }
+
+    /// Serializes the program, along with a source map for the nodes in it.
+    pub fn compile_with_source_map(
+        &mut self,
+        value: Lir,
+    ) -> Result<(Vec<u8>, SourceMap), CompileError> {
+        let (ptr, source_map) = self.compile_to_node(value)?;
+        Ok((self.serialize(ptr)?, source_map))
+    }
+
+    /// Compiles the program into the allocator, along with a source map for the nodes in
+    /// it. The node can then be serialized and hashed without compiling it again.
+    pub fn compile_to_node(&mut self, value: Lir) -> Result<(NodePtr, SourceMap), CompileError> {
+        let clvm = select(value);
+        let source_map = SourceMap::new(&clvm);
+        let ptr = self.allocate(clvm)?;
+        Ok((ptr, source_map))
+    }
+
+    pub fn serialize(&self, ptr: NodePtr) -> Result<Vec<u8>, CompileError> {
+        node_to_bytes(&self.allocator, ptr).map_err(serialize_error)
+    }
+
+    /// Serializes a node with back-references to repeated subtrees.
+    pub fn serialize_compact(&self, ptr: NodePtr) -> Result<Vec<u8>, CompileError> {
+        node_to_bytes_backrefs(&self.allocator, ptr).map_err(serialize_error)
+    }
+
+    pub fn tree_hash(&self, ptr: NodePtr) -> TreeHash {
+        tree_hash(&self.allocator, ptr)
+    }
+
+    fn compile(&mut self, value: Lir) -> Result<NodePtr, CompileError> {
+        let clvm = select(value);
+        self.allocate(clvm)
+    }
+
+    fn allocate(&mut self, clvm: Clvm) -> Result<NodePtr, CompileError> {
+        match clvm {
+            Clvm::Nil => Ok(self.nil),
+            Clvm::Path(path) => self.new_number(path.into()),
+            Clvm::Int(value) => {
+                let value = self.new_number(value)?;
+                self.quote(value)
             }
-            Lir::String(value) => {
-                if value.is_empty() {
-                    self.nil
-                } else {
-                    let value = self.allocator.new_atom(value.as_bytes()).unwrap();
-                    self.quote(value).unwrap()
-                }
+            Clvm::Bytes(value) => {
+                let value = self.new_atom(&value)?;
+                self.quote(value)
             }
-            Lir::Add(args) => {
-                let mut list = vec![self.op_add];
-                for arg in args {
-                    list.push(self.compile(arg));
-                }
-                self.new_list(&list).unwrap()
+            Clvm::Program(value) => {
+                let value = self.allocate(*value)?;
+                self.quote(value)
             }
-            Lir::Sub(args) => {
-                let mut list = vec![self.op_sub];
+            Clvm::Op(opcode, args) => {
+                let mut list = vec![self.new_atom(&[opcode.atom()])?];
                 for arg in args {
-                    list.push(self.compile(arg));
+                    list.push(self.allocate(arg)?);
                 }
-                self.new_list(&list).unwrap()
+                self.new_list(&list)
             }
-            Lir::Mul(args) => {
-                let mut list = vec![self.op_mul];
-                for arg in args {
-                    list.push(self.compile(arg));
-                }
-                self.new_list(&list).unwrap()
+            // Errors point at the innermost expression in the source that they happened in.
+            Clvm::Span(range, value) => self.allocate(*value).map_err(|error| error.within(range)),
+        }
+    }
+
+    fn quote(&mut self, value: NodePtr) -> Result<NodePtr, CompileError> {
+        self.new_pair(self.op_q, value)
+    }
+
+    fn new_list(&mut self, values: &[NodePtr]) -> Result<NodePtr, CompileError> {
+        let mut result = self.nil;
+        for value in values.iter().rev() {
+            result = self.new_pair(*value, result)?;
+        }
+        Ok(result)
+    }
+
+    fn new_number(&mut self, value: BigInt) -> Result<NodePtr, CompileError> {
+        if value.is_zero() {
+            return Ok(self.nil);
+        }
+        self.new_atom(&value.to_signed_bytes_be())
+    }
+
+    fn new_atom(&mut self, value: &[u8]) -> Result<NodePtr, CompileError> {
+        if let Some(ptr) = self.atoms.get(value) {
+            return Ok(*ptr);
+        }
+        let ptr = self.allocator.new_atom(value).map_err(allocator_error)?;
+        self.atoms.insert(value.to_vec(), ptr);
+        Ok(ptr)
+    }
+
+    fn new_pair(&mut self, first: NodePtr, rest: NodePtr) -> Result<NodePtr, CompileError> {
+        if let Some(ptr) = self.pairs.get(&(first, rest)) {
+            return Ok(*ptr);
+        }
+        let ptr = self
+            .allocator
+            .new_pair(first, rest)
+            .map_err(allocator_error)?;
+        self.pairs.insert((first, rest), ptr);
+        Ok(ptr)
+    }
+}
+
+fn allocator_error(error: EvalErr) -> CompileError {
+    CompileErrorKind::Allocator(error.1).into()
+}
+
+fn serialize_error(error: io::Error) -> CompileError {
+    CompileErrorKind::Serialize(error.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use clvmr::{
+        allocator::NodePtr,
+        reduction::{EvalErr, Reduction},
+        run_program,
+        serde::{node_from_bytes, node_from_bytes_backrefs},
+        Allocator, ChiaDialect,
+    };
+    use num_bigint::BigInt;
+    use rowan::ast::AstNode;
+    use rue_ast::Program;
+    use rue_lir::{OptLevel, Pass, PassManager};
+    use rue_parser::parse_text;
+
+    use super::*;
+
+    fn compile(source: &str, passes: &PassManager) -> Vec<u8> {
+        let (errors, node) = parse_text(source);
+        assert_eq!(errors, Vec::new());
+
+        let output = rue_hir::lower(Program::cast(node).unwrap());
+        assert_eq!(output.errors, Vec::new());
+
+        let lir = rue_lir::lower(output.db, output.scope, passes).unwrap();
+        Compiler::new().compile_to_bytes(passes.run(lir)).unwrap()
+    }
+
+    fn run(bytes: &[u8], solution: &[i64]) -> (BigInt, u64) {
+        try_run(bytes, solution).unwrap()
+    }
+
+    fn try_run(bytes: &[u8], solution: &[i64]) -> Result<(BigInt, u64), EvalErr> {
+        let mut allocator = Allocator::new();
+        let program = node_from_bytes(&mut allocator, bytes).unwrap();
+
+        let mut env = allocator.null();
+        for value in solution.iter().rev() {
+            let value = allocator.new_number((*value).into()).unwrap();
+            env = allocator.new_pair(value, env).unwrap();
+        }
+
+        let Reduction(cost, output) =
+            run_program(&mut allocator, &ChiaDialect::new(0), program, env, u64::MAX)?;
+        Ok((allocator.number(output), cost))
+    }
+
+    const NESTED_CALLS: &str = r#"
+        fn main(x: Int) -> Int {
+            f(x) + g(x)
+        }
+
+        fn f(a: Int) -> Int {
+            g(a) * 2
+        }
+
+        fn g(a: Int) -> Int {
+            a + 1
+        }
+    "#;
+
+    /// Checks the output of a program with every combination of lowering options.
+    fn check(source: &str, solution: &[i64], expected: i64) {
+        for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+            let bytes = compile(source, &PassManager::new(level));
+            assert_eq!(run(&bytes, solution).0, expected.into(), "-O{level}");
+        }
+    }
+
+    #[test]
+    fn nested_calls() {
+        check(NESTED_CALLS, &[5], 18);
+    }
+
+    #[test]
+    fn string_literals() {
+        // The quotes are part of the literal's syntax, not its value.
+        let source = r#"
+            fn main() -> String {
+                "abc"
             }
-            Lir::Div(args) => {
-                let mut list = vec![self.op_div];
-                for arg in args {
-                    list.push(self.compile(arg));
-                }
-                self.new_list(&list).unwrap()
+        "#;
+        check(source, &[], 0x61_62_63);
+    }
+
+    #[test]
+    fn recursion() {
+        let source = r#"
+            fn main(n: Int) -> Int {
+                factorial(n)
             }
-            Lir::Lt(a, b) => {
-                let a = self.compile(a.as_ref().clone());
-                let b = self.compile(b.as_ref().clone());
-                let gt = self.new_list(&[self.op_gt, a, b]).unwrap();
-                let eq = self.new_list(&[self.op_eq, a, b]).unwrap();
-                let any = self.new_list(&[self.op_any, gt, eq]).unwrap();
-                self.new_list(&[self.op_not, any]).unwrap()
+
+            fn factorial(n: Int) -> Int {
+                if n < 2 { 1 } else { n * factorial(n - 1) }
             }
-            Lir::Gt(a, b) => {
-                let a = self.compile(a.as_ref().clone());
-                let b = self.compile(b.as_ref().clone());
-                self.new_list(&[self.op_gt, a, b]).unwrap()
+        "#;
+        check(source, &[5], 120);
+    }
+
+    #[test]
+    fn recursion_through_let() {
+        let source = r#"
+            fn main(n: Int) -> Int {
+                depth(n)
             }
-            Lir::Path(path) => self.allocator.new_number(path.into()).unwrap(),
-            Lir::Environment {
-                value,
-                arguments,
-                rest,
-            } => {
-                let value = self.compile(value.as_ref().clone());
-                let arguments = arguments
-                    .into_iter()
-                    .map(|input| self.compile(input))
-                    .collect::<Vec<_>>();
-                let rest = rest
-                    .map(|value| self.compile(value.as_ref().clone()))
-                    .unwrap_or(self.nil);
 
-                let arguments = self.build_cons(&arguments, rest).unwrap();
-                self.new_list(&[self.op_a, value, arguments]).unwrap()
+            fn depth(n: Int) -> Int {
+                let rest = if n < 1 { 0 } else { depth(n - 1) };
+                rest + 1
+            }
+        "#;
+        check(source, &[3], 4);
+
+        let mut small_only = PassManager::default();
+        small_only.disable(Pass::Inline);
+        assert_eq!(run(&compile(source, &small_only), &[3]).0, 4.into());
+    }
+
+    #[test]
+    fn inline_arguments_in_branches() {
+        let source = r#"
+            fn main(flag: Int) -> Int {
+                pick(flag, 1 / 0)
+            }
+
+            fn pick(flag: Int, value: Int) -> Int {
+                if flag > 0 { value } else { 0 }
+            }
+        "#;
+
+        let expected = try_run(&compile(source, &PassManager::new(OptLevel::O0)), &[0])
+            .expect_err("division by zero")
+            .1;
+
+        for level in [OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+            let bytes = compile(source, &PassManager::new(level));
+            let error = try_run(&bytes, &[0]).expect_err("division by zero");
+            assert_eq!(error.1, expected, "-O{level}");
+        }
+    }
+
+    #[test]
+    fn inline_reduces_cost() {
+        let source = r#"
+            fn main(x: Int) -> Int {
+                square(x + 1) + square(x) + double(x)
+            }
+
+            inline fn square(a: Int) -> Int {
+                a * a
+            }
+
+            fn double(a: Int) -> Int {
+                a + a
+            }
+        "#;
+
+        let mut without_inlining = PassManager::default();
+        without_inlining.disable(Pass::Inline);
+        without_inlining.disable(Pass::InlineSmall);
+
+        let inlined = compile(source, &PassManager::default());
+        let called = compile(source, &without_inlining);
+
+        let (inlined_output, inlined_cost) = run(&inlined, &[3]);
+        let (called_output, called_cost) = run(&called, &[3]);
+
+        assert_eq!(inlined_output, 31.into());
+        assert_eq!(inlined_output, called_output);
+        assert!(
+            inlined_cost < called_cost,
+            "inlining should be cheaper ({inlined_cost} >= {called_cost})"
+        );
+    }
+
+    const MANY_PARAMETERS: &str = r#"
+        fn main(x: Int) -> Int {
+            sum(x, 1, 2, 3, 4, 5, 6, 7, 8, x) + sum(1, 2, 3, 4, 5, 6, 7, 8, 9, x)
+        }
+
+        fn sum(
+            a: Int, b: Int, c: Int, d: Int, e: Int,
+            f: Int, g: Int, h: Int, i: Int, j: Int
+        ) -> Int {
+            j * (a + b + c + d + e + f + g + h + i) + j * j
+        }
+    "#;
+
+    #[test]
+    fn many_parameters() {
+        check(MANY_PARAMETERS, &[2], 174);
+    }
+
+    #[test]
+    fn balanced_environment_reduces_cost() {
+        let mut without_layout = PassManager::default();
+        without_layout.disable(Pass::EnvironmentLayout);
+
+        let tree = compile(MANY_PARAMETERS, &PassManager::default());
+        let list = compile(MANY_PARAMETERS, &without_layout);
+
+        let (tree_output, tree_cost) = run(&tree, &[2]);
+        let (list_output, list_cost) = run(&list, &[2]);
+
+        assert_eq!(tree_output, list_output);
+        assert!(
+            tree_cost < list_cost,
+            "balanced environment should be cheaper ({tree_cost} >= {list_cost})"
+        );
+    }
+
+    const LET_BINDINGS: &str = r#"
+        fn main(n: Int) -> Int {
+            let a = n + 1;
+            let b = a * a;
+            let c = n * 3;
+            square_sum(b, c) + twice(a)
+        }
+
+        fn square_sum(x: Int, y: Int) -> Int {
+            let sum = x + y;
+            sum * sum
+        }
+
+        fn twice(x: Int) -> Int {
+            let doubled = x * 2;
+            if doubled > 100 { twice(doubled / 4) } else { doubled }
+        }
+    "#;
+
+    #[test]
+    fn let_bindings() {
+        check(LET_BINDINGS, &[2], 231);
+    }
+
+    #[test]
+    fn variables_are_evaluated_in_their_block() {
+        let source = r#"
+            fn main(x: Int) -> Int {
+                if x > 0 {
+                    let q = 100 / x;
+                    q
+                } else {
+                    0
+                }
             }
-            Lir::Quote(value) => {
-                let value = self.compile(value.as_ref().clone());
-                self.quote(value).unwrap()
+        "#;
+        check(source, &[0], 0);
+        check(source, &[4], 25);
+    }
+
+    #[test]
+    fn unreachable_functions_are_not_compiled() {
+        let live = r#"
+            fn main(n: Int) -> Int { helper(n) * helper(n + 1) }
+            fn helper(n: Int) -> Int { if n > 1 { helper(n - 1) + n } else { 1 } }
+        "#;
+        let dead = format!(
+            "{live}
+            fn unused(n: Int) -> Int {{ helper(n) + also_unused(n) }}
+            fn also_unused(n: Int) -> Int {{ unused(n) }}
+            "
+        );
+
+        for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+            let passes = PassManager::new(level);
+            assert_eq!(compile(live, &passes), compile(&dead, &passes), "-O{level}");
+        }
+    }
+
+    /// Assembles a program by hand, to compare against what the compiler selects.
+    fn assemble(build: impl FnOnce(&mut Assembler) -> NodePtr) -> Vec<u8> {
+        let mut assembler = Assembler(Allocator::new());
+        let program = build(&mut assembler);
+        node_to_bytes(&assembler.0, program).unwrap()
+    }
+
+    struct Assembler(Allocator);
+
+    impl Assembler {
+        fn atom(&mut self, value: i64) -> NodePtr {
+            self.0.new_number(value.into()).unwrap()
+        }
+
+        fn quote(&mut self, value: NodePtr) -> NodePtr {
+            let q = self.0.one();
+            self.0.new_pair(q, value).unwrap()
+        }
+
+        fn op(&mut self, opcode: u8, args: &[NodePtr]) -> NodePtr {
+            let mut list = self.0.null();
+            for arg in args.iter().rev() {
+                list = self.0.new_pair(*arg, list).unwrap();
             }
-            Lir::If {
-                condition,
-                then_branch,
-                else_branch,
-            } => {
-                let condition = self.compile(condition.as_ref().clone());
-                let then_branch = self.compile(then_branch.as_ref().clone());
-                let else_branch = self.compile(else_branch.as_ref().clone());
+            let opcode = self.0.new_atom(&[opcode]).unwrap();
+            self.0.new_pair(opcode, list).unwrap()
+        }
 
-                let quoted_then = self.quote(then_branch).unwrap();
-                let quoted_else = self.quote(else_branch).unwrap();
+        /// The old expansion of `a < b`, which is `(not (any (> a b) (= a b)))`.
+        fn naive_lt(&mut self, lhs: i64, rhs: i64) -> NodePtr {
+            let (lhs, rhs) = (self.atom(lhs), self.atom(rhs));
+            let gt = self.op(21, &[lhs, rhs]);
+            let eq = self.op(9, &[lhs, rhs]);
+            let any = self.op(33, &[gt, eq]);
+            self.op(32, &[any])
+        }
 
-                let ptr = self
-                    .new_list(&[self.op_i, condition, quoted_then, quoted_else])
-                    .unwrap();
+        /// An `if` which always applies the branch that is taken.
+        fn naive_if(
+            &mut self,
+            condition: NodePtr,
+            then_branch: NodePtr,
+            else_branch: NodePtr,
+        ) -> NodePtr {
+            let then_branch = self.quote(then_branch);
+            let else_branch = self.quote(else_branch);
+            let branch = self.op(3, &[condition, then_branch, else_branch]);
+            let env = self.atom(1);
+            self.op(2, &[branch, env])
+        }
+    }
+
+    fn compile_lir(lir: Lir) -> Vec<u8> {
+        Compiler::new().compile_to_bytes(lir).unwrap()
+    }
+
+    /// Asserts that the selected program has the same output as the naive one for every
+    /// solution, and that it is always cheaper.
+    fn assert_cheaper(selected: &[u8], naive: &[u8], solutions: &[[i64; 2]]) {
+        for solution in solutions {
+            let (selected_output, selected_cost) = run(selected, solution);
+            let (naive_output, naive_cost) = run(naive, solution);
+            assert_eq!(selected_output, naive_output, "{solution:?}");
+            assert!(
+                selected_cost < naive_cost,
+                "{solution:?} costs {selected_cost}, expected less than {naive_cost}"
+            );
+        }
+    }
+
+    const COMPARISON_SOLUTIONS: [[i64; 2]; 5] = [[1, 2], [2, 1], [3, 3], [-5, 0], [1000, -1000]];
+
+    fn less_than() -> Lir {
+        Lir::Lt(Box::new(Lir::Path(2)), Box::new(Lir::Path(5)))
+    }
+
+    fn branch(condition: Lir, then_branch: Lir, else_branch: Lir) -> Lir {
+        Lir::If {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        }
+    }
+
+    #[test]
+    fn less_than_cost() {
+        assert_cheaper(
+            &compile_lir(less_than()),
+            &assemble(|asm| asm.naive_lt(2, 5)),
+            &COMPARISON_SOLUTIONS,
+        );
+    }
+
+    #[test]
+    fn boolean_branch_cost() {
+        let selected = compile_lir(branch(less_than(), Lir::Int(1.into()), Lir::Int(0.into())));
+        let naive = assemble(|asm| {
+            let condition = asm.naive_lt(2, 5);
+            let one = asm.atom(1);
+            let then_branch = asm.quote(one);
+            let else_branch = asm.0.null();
+            asm.naive_if(condition, then_branch, else_branch)
+        });
+        assert_cheaper(&selected, &naive, &COMPARISON_SOLUTIONS);
+    }
+
+    #[test]
+    fn negated_branch_cost() {
+        let negated = branch(less_than(), Lir::Int(0.into()), Lir::Int(1.into()));
+        let selected = compile_lir(branch(negated, Lir::Path(2), Lir::Path(5)));
+        let naive = assemble(|asm| {
+            let condition = asm.naive_lt(2, 5);
+            let zero = asm.0.null();
+            let one = asm.atom(1);
+            let one = asm.quote(one);
+            let negated = asm.naive_if(condition, zero, one);
+            let (lhs, rhs) = (asm.atom(2), asm.atom(5));
+            asm.naive_if(negated, lhs, rhs)
+        });
+        assert_cheaper(&selected, &naive, &COMPARISON_SOLUTIONS);
+    }
+
+    #[test]
+    fn constant_branch_cost() {
+        let selected = compile_lir(branch(
+            Lir::Path(2),
+            Lir::Int(10.into()),
+            Lir::Int(20.into()),
+        ));
+        let naive = assemble(|asm| {
+            let condition = asm.atom(2);
+            let ten = asm.atom(10);
+            let ten = asm.quote(ten);
+            let twenty = asm.atom(20);
+            let twenty = asm.quote(twenty);
+            asm.naive_if(condition, ten, twenty)
+        });
+        assert_cheaper(&selected, &naive, &[[0, 0], [1, 0], [-1, 0]]);
+    }
 
-                self.new_list(&[self.op_a, ptr, self.one]).unwrap()
+    #[test]
+    fn byte_string_comparison() {
+        let source = r#"
+            fn main(a: String, b: String) -> Int {
+                if a < b { 1 } else { 0 }
             }
+        "#;
+
+        // `"abc" < "b"` as byte strings, even though `0x616263 > 0x62` as integers.
+        let bytes = compile(source, &PassManager::default());
+        assert_eq!(run(&bytes, &[0x616263, 0x62]).0, 1.into());
+        assert_eq!(run(&bytes, &[0x62, 0x616263]).0, 0.into());
+        assert_eq!(run(&bytes, &[0x62, 0x62]).0, 0.into());
+    }
+
+    const DUPLICATED_BODIES: &str = r#"
+        fn main(n: Int) -> Int {
+            first(n) + second(n) * third(n)
         }
+
+        fn first(x: Int) -> Int { if x > 10 { x * x * x - 7 } else { x * x + x * 2 + 5 } }
+        fn second(x: Int) -> Int { if x > 10 { x * x * x - 7 } else { x * x + x * 2 + 5 } }
+        fn third(x: Int) -> Int { if x > 10 { x * x * x - 7 } else { x * x + x * 2 + 5 } }
+    "#;
+
+    #[test]
+    fn identical_subtrees_are_shared() {
+        let body = Lir::Quote(Box::new(Lir::Add(vec![
+            Lir::Path(2),
+            Lir::Int(1000.into()),
+        ])));
+
+        let mut compiler = Compiler::new();
+        let ptr = compiler
+            .compile(Lir::Cons(Box::new(body.clone()), Box::new(body)))
+            .unwrap();
+
+        let clvmr::allocator::SExp::Pair(_, args) = compiler.allocator.sexp(ptr) else {
+            panic!("expected a pair");
+        };
+        let clvmr::allocator::SExp::Pair(first, rest) = compiler.allocator.sexp(args) else {
+            panic!("expected a pair");
+        };
+        let clvmr::allocator::SExp::Pair(second, _) = compiler.allocator.sexp(rest) else {
+            panic!("expected a pair");
+        };
+        assert_eq!(first, second);
     }
 
-    fn quote(&mut self, value: NodePtr) -> Result<NodePtr, EvalErr> {
-        self.allocator.new_pair(self.op_q, value)
+    #[test]
+    fn back_references_reduce_size() {
+        let mut passes = PassManager::default();
+        passes.disable(Pass::Inline);
+        passes.disable(Pass::InlineSmall);
+
+        let (errors, node) = parse_text(DUPLICATED_BODIES);
+        assert_eq!(errors, Vec::new());
+        let output = rue_hir::lower(Program::cast(node).unwrap());
+        let lir = rue_lir::lower(output.db, output.scope, &passes).unwrap();
+        let lir = passes.run(lir);
+
+        let mut compiler = Compiler::new();
+        let plain = compiler.compile_to_bytes(lir.clone()).unwrap();
+        let compact = compiler.compile_to_compact_bytes(lir).unwrap();
+
+        assert!(
+            compact.len() < plain.len(),
+            "{} bytes with back-references, {} without",
+            compact.len(),
+            plain.len()
+        );
+
+        let mut allocator = Allocator::new();
+        let program = node_from_bytes_backrefs(&mut allocator, &compact).unwrap();
+        assert_eq!(node_to_bytes(&allocator, program).unwrap(), plain);
+
+        let value = 3 * 3 + 3 * 2 + 5;
+        assert_eq!(run(&plain, &[3]).0, (value + value * value).into());
     }
 
-    fn new_list(&mut self, values: &[NodePtr]) -> Result<NodePtr, EvalErr> {
-        let mut result = self.nil;
-        for value in values.iter().rev() {
-            result = self.allocator.new_pair(*value, result)?;
+    const DIVISION: &str = r#"
+        fn main(x: Int, y: Int) -> Int {
+            half(x) + x / y
         }
-        Ok(result)
+
+        fn half(a: Int) -> Int {
+            a / 2
+        }
+    "#;
+
+    fn trace(source: &str, passes: &PassManager, solution: &[i64]) -> Option<String> {
+        let (errors, node) = parse_text(source);
+        assert_eq!(errors, Vec::new());
+        let output = rue_hir::lower(Program::cast(node).unwrap());
+        let lir = rue_lir::lower(output.db, output.scope, passes).unwrap();
+        let (bytes, source_map) = Compiler::new()
+            .compile_with_source_map(passes.run(lir))
+            .unwrap();
+
+        let mut allocator = Allocator::new();
+        let program = node_from_bytes(&mut allocator, &bytes).unwrap();
+        let mut env = allocator.null();
+        for value in solution.iter().rev() {
+            let value = allocator.new_number((*value).into()).unwrap();
+            env = allocator.new_pair(value, env).unwrap();
+        }
+
+        let error = run_traced(&mut allocator, program, env, u64::MAX, &source_map).unwrap_err();
+        assert!(matches!(error.error, RunError::Eval { .. }));
+
+        let range = error.range?;
+        Some(
+            source[range.from().pos()..range.to().pos()]
+                .trim()
+                .to_string(),
+        )
     }
 
-    fn build_cons(&mut self, values: &[NodePtr], terminator: NodePtr) -> Result<NodePtr, EvalErr> {
-        let mut result = terminator;
-        for value in values.iter().rev() {
-            result = self.new_list(&[self.op_c, *value, result])?;
+    #[test]
+    fn runtime_errors_are_traced_to_source() {
+        for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+            let passes = PassManager::new(level);
+            assert_eq!(trace(DIVISION, &passes, &[10, 0]).as_deref(), Some("x / y"));
+            assert_eq!(trace(DIVISION, &passes, &[10]).as_deref(), Some("x / y"));
         }
-        Ok(result)
+    }
+
+    #[test]
+    fn source_maps_do_not_change_the_program() {
+        let passes = PassManager::default();
+        let (errors, node) = parse_text(DIVISION);
+        assert_eq!(errors, Vec::new());
+        let output = rue_hir::lower(Program::cast(node).unwrap());
+        let lir = passes.run(rue_lir::lower(output.db, output.scope, &passes).unwrap());
+
+        let (bytes, source_map) = Compiler::new()
+            .compile_with_source_map(lir.clone())
+            .unwrap();
+        assert_eq!(bytes, Compiler::new().compile_to_bytes(lir).unwrap());
+        assert!(!source_map.is_empty());
+    }
+
+    #[test]
+    fn curried_parameters_come_before_the_solution() {
+        let source = r#"
+            fn main(curry base: Int, curry scale: Int, x: Int) -> Int {
+                scaled(x) * scale + base
+            }
+
+            fn scaled(x: Int) -> Int {
+                if x > 0 { scaled(x - 1) + 1 } else { 0 }
+            }
+        "#;
+
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let bytes = compile(source, &PassManager::new(level));
+
+            let mut allocator = Allocator::new();
+            let program = node_from_bytes(&mut allocator, &bytes).unwrap();
+            let base = allocator.new_number(100.into()).unwrap();
+            let scale = allocator.new_number(7.into()).unwrap();
+            let curried = curry(&mut allocator, program, &[base, scale]).unwrap();
+
+            let x = allocator.new_number(5.into()).unwrap();
+            let nil = allocator.null();
+            let solution = allocator.new_pair(x, nil).unwrap();
+
+            let Reduction(_, output) = run_program(
+                &mut allocator,
+                &ChiaDialect::new(0),
+                curried,
+                solution,
+                u64::MAX,
+            )
+            .unwrap();
+            assert_eq!(allocator.number(output), 135.into());
+        }
+    }
+
+    #[test]
+    fn conditions_compile_to_opcode_lists() {
+        let source = r#"
+            fn main(amount: Int, fee: Int) -> [Condition] {
+                if fee > 0 {
+                    [create_coin("puzzle hash", amount - fee), reserve_fee(fee)]
+                } else {
+                    []
+                }
+            }
+        "#;
+
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let bytes = compile(source, &PassManager::new(level));
+            let mut allocator = Allocator::new();
+            let program = node_from_bytes(&mut allocator, &bytes).unwrap();
+
+            let solution = crate::assemble(&mut allocator, "(100 3)").unwrap();
+            let (value, _) = crate::run(&mut allocator, program, solution, u64::MAX).unwrap();
+            assert_eq!(
+                disassemble_value(&allocator, value),
+                "((51 \"puzzle hash\" 97) (52 3))"
+            );
+
+            let solution = crate::assemble(&mut allocator, "(100 0)").unwrap();
+            let (value, _) = crate::run(&mut allocator, program, solution, u64::MAX).unwrap();
+            assert_eq!(disassemble_value(&allocator, value), "()");
+        }
+    }
+
+    #[test]
+    fn tests_compile_as_their_own_program() {
+        let source = r#"
+            fn main(x: Int) -> Int { x }
+            fn square(x: Int) -> Int { x * x }
+
+            #[test]
+            fn squares() -> Int { square(7) }
+        "#;
+
+        let (_, node) = parse_text(source);
+        let output = rue_hir::lower(Program::cast(node).unwrap());
+        assert_eq!(output.errors, Vec::new());
+
+        let scope = output.scope;
+        let tests = rue_hir::test_functions(&output.db, &scope);
+        assert_eq!(tests.len(), 1);
+
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let passes = PassManager::new(level);
+            let lir = rue_lir::lower_entry(output.db.clone(), &scope, tests[0].1, &passes);
+            let bytes = Compiler::new()
+                .compile_to_bytes(passes.run(lir.unwrap()))
+                .unwrap();
+            assert_eq!(run(&bytes, &[]).0, BigInt::from(49));
+        }
+    }
+
+    #[test]
+    fn lowering_errors() {
+        let lower = |source: &str| {
+            let (_, node) = parse_text(source);
+            let output = rue_hir::lower(Program::cast(node).unwrap());
+            rue_lir::lower(output.db, output.scope, &PassManager::new(OptLevel::O0)).unwrap_err()
+        };
+
+        let error = lower("fn helper() -> Int { 1 }");
+        assert_eq!(
+            error.kind,
+            CompileErrorKind::MissingEntry("main".to_string())
+        );
+        assert_eq!(error.range, None);
+
+        // The HIR reports the undefined variable and replaces the operation around it with
+        // an error, so lowering points at the innermost expression that contains it.
+        let source = "fn main() -> Int { 1 + (2 * missing) }";
+        let error = lower(source);
+        assert_eq!(error.kind, CompileErrorKind::ErrorExpression);
+        let start = source.find('1').unwrap();
+        assert_eq!(
+            error.range.map(|range| range.trim_whitespace(source)),
+            Some((start..start + 17).into())
+        );
     }
 }
@@ -0,0 +1,162 @@
+use std::{cell::RefCell, error, fmt, rc::Rc};
+
+use clvmr::{
+    allocator::NodePtr,
+    reduction::{EvalErr, Reduction},
+    run_program,
+    run_program::PostEval,
+    run_program_with_pre_eval, Allocator, ChiaDialect,
+};
+use rue_error::TextRange;
+
+use crate::{disassemble_value, SourceMap};
+
+/// The cost limit of a single block, which is also the most a spend can ever cost.
+pub const DEFAULT_MAX_COST: u64 = 11_000_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError {
+    /// The program raised an exception with `x`, along with the values it was given.
+    Raise(String),
+    /// The program would have cost more than the limit.
+    CostExceeded(u64),
+    /// Any other failure, such as an invalid operator or an argument of the wrong type.
+    Eval { message: String, value: String },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Raise(value) => write!(f, "program raised an exception with {value}"),
+            Self::CostExceeded(max_cost) => {
+                write!(f, "program exceeded the maximum cost of {max_cost}")
+            }
+            Self::Eval { message, value } => write!(f, "{message} at {value}"),
+        }
+    }
+}
+
+impl error::Error for RunError {}
+
+/// A runtime error, along with the source range of the innermost expression
+/// that was being evaluated when it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedError {
+    pub error: RunError,
+    pub range: Option<TextRange>,
+}
+
+impl fmt::Display for TracedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.range {
+            Some(range) => write!(f, "{} in the expression at {range}", self.error),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+impl error::Error for TracedError {}
+
+/// Runs a program with the given solution, and returns the result and the exact cost.
+pub fn run(
+    allocator: &mut Allocator,
+    program: NodePtr,
+    solution: NodePtr,
+    max_cost: u64,
+) -> Result<(NodePtr, u64), RunError> {
+    match run_program(allocator, &ChiaDialect::new(0), program, solution, max_cost) {
+        Ok(Reduction(cost, value)) => Ok((value, cost)),
+        Err(error) => Err(run_error(allocator, error, max_cost)),
+    }
+}
+
+/// Runs a program like [`run`], but keeps track of which expressions in the source map
+/// are being evaluated, so that an error can be traced back to where it happened.
+pub fn run_traced(
+    allocator: &mut Allocator,
+    program: NodePtr,
+    solution: NodePtr,
+    max_cost: u64,
+    source_map: &SourceMap,
+) -> Result<(NodePtr, u64), TracedError> {
+    let ranges = source_map.resolve(allocator, program);
+    let stack = Rc::new(RefCell::new(Vec::new()));
+
+    let pre_eval = {
+        let stack = stack.clone();
+        Box::new(move |_: &mut Allocator, program: NodePtr, _: NodePtr| {
+            let Some(range) = ranges.get(&program) else {
+                return Ok(None);
+            };
+            stack.borrow_mut().push(*range);
+
+            let stack = stack.clone();
+            let post_eval: Box<PostEval> = Box::new(move |_| {
+                stack.borrow_mut().pop();
+            });
+            Ok(Some(post_eval))
+        })
+    };
+
+    let result = run_program_with_pre_eval(
+        allocator,
+        &ChiaDialect::new(0),
+        program,
+        solution,
+        max_cost,
+        Some(pre_eval),
+    );
+
+    match result {
+        Ok(Reduction(cost, value)) => Ok((value, cost)),
+        Err(error) => Err(TracedError {
+            error: run_error(allocator, error, max_cost),
+            range: stack.borrow().last().copied(),
+        }),
+    }
+}
+
+fn run_error(allocator: &Allocator, error: EvalErr, max_cost: u64) -> RunError {
+    let EvalErr(node, message) = error;
+    let value = disassemble_value(allocator, node);
+    match message.as_str() {
+        "clvm raise" => RunError::Raise(value),
+        "cost exceeded" => RunError::CostExceeded(max_cost),
+        _ => RunError::Eval { message, value },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assemble, disassemble};
+
+    use super::*;
+
+    fn check(program: &str, solution: &str, max_cost: u64) -> Result<(String, u64), RunError> {
+        let mut allocator = Allocator::new();
+        let program = assemble(&mut allocator, program).unwrap();
+        let solution = assemble(&mut allocator, solution).unwrap();
+        let (value, cost) = run(&mut allocator, program, solution, max_cost)?;
+        Ok((disassemble(&allocator, value), cost))
+    }
+
+    #[test]
+    fn run_programs() {
+        assert_eq!(
+            check("(+ 2 5)", "(40 2)", DEFAULT_MAX_COST),
+            Ok(("42".to_string(), 856))
+        );
+        assert_eq!(
+            check("(x 2)", "(\"failed\")", DEFAULT_MAX_COST),
+            Err(RunError::Raise("\"failed\"".to_string()))
+        );
+        assert_eq!(
+            check("(+ 2 5)", "(40 2)", 100),
+            Err(RunError::CostExceeded(100))
+        );
+        assert!(matches!(
+            check("(+ 2 5)", "((1 2) 3)", DEFAULT_MAX_COST),
+            Err(RunError::Eval { .. })
+        ));
+    }
+}
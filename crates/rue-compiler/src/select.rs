@@ -0,0 +1,254 @@
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+use rue_error::TextRange;
+use rue_lir::Lir;
+
+/// The CLVM operators which the compiler emits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Opcode {
+    Apply,
+    If,
+    Cons,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Gt,
+    GtBytes,
+    Not,
+}
+
+impl Opcode {
+    pub fn atom(self) -> u8 {
+        match self {
+            Self::Apply => 2,
+            Self::If => 3,
+            Self::Cons => 4,
+            Self::GtBytes => 10,
+            Self::Add => 16,
+            Self::Sub => 17,
+            Self::Mul => 18,
+            Self::Div => 19,
+            Self::Gt => 21,
+            Self::Not => 32,
+        }
+    }
+}
+
+/// A CLVM program, once an operator has been chosen for each `Lir` node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Clvm {
+    /// The empty atom, which doesn't need to be quoted.
+    Nil,
+    /// A path into the environment.
+    Path(usize),
+    /// A quoted integer.
+    Int(BigInt),
+    /// A quoted byte string.
+    Bytes(Vec<u8>),
+    /// A quoted program, which is returned as a value rather than being evaluated.
+    Program(Box<Clvm>),
+    Op(Opcode, Vec<Clvm>),
+    /// The source range that a value was compiled from, which is recorded in the source map.
+    Span(TextRange, Box<Clvm>),
+}
+
+impl Clvm {
+    fn unspanned(&self) -> &Clvm {
+        match self {
+            Self::Span(_, value) => value.unspanned(),
+            _ => self,
+        }
+    }
+
+    /// Whether the value is known to be either `1` or nil, which is the case for comparisons.
+    fn is_boolean(&self) -> bool {
+        is_one(self)
+            || matches!(
+                self.unspanned(),
+                Self::Nil | Self::Op(Opcode::Gt | Opcode::GtBytes | Opcode::Not, _)
+            )
+    }
+
+    /// Whether evaluating the value is free of errors and cheap enough to do eagerly.
+    fn is_constant(&self) -> bool {
+        matches!(
+            self.unspanned(),
+            Self::Nil | Self::Int(_) | Self::Bytes(_) | Self::Program(_)
+        )
+    }
+}
+
+/// Picks the cheapest CLVM form for each operator in the program.
+pub(crate) fn select(lir: Lir) -> Clvm {
+    let select_all = |lirs: Vec<Lir>| lirs.into_iter().map(select).collect();
+
+    match lir {
+        Lir::Int(value) if value.is_zero() => Clvm::Nil,
+        Lir::Int(value) => Clvm::Int(value),
+        Lir::String(value) if value.is_empty() => Clvm::Nil,
+        Lir::String(value) => Clvm::Bytes(value.into_bytes()),
+        Lir::Path(path) => Clvm::Path(path),
+        Lir::Add(args) => Clvm::Op(Opcode::Add, select_all(args)),
+        Lir::Sub(args) => Clvm::Op(Opcode::Sub, select_all(args)),
+        Lir::Mul(args) => Clvm::Op(Opcode::Mul, select_all(args)),
+        Lir::Div(args) => Clvm::Op(Opcode::Div, select_all(args)),
+        // `a < b` is the same as `b > a`, which only needs a single operator.
+        Lir::Lt(lhs, rhs) => Clvm::Op(Opcode::Gt, vec![select(*rhs), select(*lhs)]),
+        Lir::Gt(lhs, rhs) => Clvm::Op(Opcode::Gt, vec![select(*lhs), select(*rhs)]),
+        Lir::GtBytes(lhs, rhs) => Clvm::Op(Opcode::GtBytes, vec![select(*lhs), select(*rhs)]),
+        Lir::Cons(first, rest) => Clvm::Op(Opcode::Cons, vec![select(*first), select(*rest)]),
+        Lir::Environment { value, environment } => {
+            Clvm::Op(Opcode::Apply, vec![select(*value), select(*environment)])
+        }
+        Lir::Quote(value) => Clvm::Program(Box::new(select(*value))),
+        Lir::Span(range, value) => Clvm::Span(range, Box::new(select(*value))),
+        Lir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => select_if(
+            select(*condition),
+            select(*then_branch),
+            select(*else_branch),
+        ),
+    }
+}
+
+fn select_if(mut condition: Clvm, mut then_branch: Clvm, mut else_branch: Clvm) -> Clvm {
+    // Branching on a negated condition is the same as swapping the branches.
+    while let Some(inner) = negated(&mut condition) {
+        condition = inner;
+        std::mem::swap(&mut then_branch, &mut else_branch);
+    }
+
+    if is_one(&then_branch) && else_branch == Clvm::Nil && condition.is_boolean() {
+        return condition;
+    }
+
+    if then_branch == Clvm::Nil && is_one(&else_branch) {
+        return not(condition);
+    }
+
+    // Constant branches can be evaluated eagerly, which avoids having to apply the result.
+    if then_branch.is_constant() && else_branch.is_constant() {
+        return Clvm::Op(Opcode::If, vec![condition, then_branch, else_branch]);
+    }
+
+    Clvm::Op(
+        Opcode::Apply,
+        vec![
+            Clvm::Op(
+                Opcode::If,
+                vec![
+                    condition,
+                    Clvm::Program(Box::new(then_branch)),
+                    Clvm::Program(Box::new(else_branch)),
+                ],
+            ),
+            Clvm::Path(1),
+        ],
+    )
+}
+
+/// Takes the operand out of a `not`, if the value is one.
+fn negated(clvm: &mut Clvm) -> Option<Clvm> {
+    match clvm {
+        Clvm::Span(_, value) => negated(value),
+        Clvm::Op(Opcode::Not, args) if args.len() == 1 => Some(args.remove(0)),
+        _ => None,
+    }
+}
+
+fn is_one(clvm: &Clvm) -> bool {
+    matches!(clvm.unspanned(), Clvm::Int(value) if value.is_one())
+}
+
+fn not(value: Clvm) -> Clvm {
+    match value {
+        Clvm::Op(Opcode::Not, mut args) if args.len() == 1 && args[0].is_boolean() => {
+            args.remove(0)
+        }
+        value => Clvm::Op(Opcode::Not, vec![value]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(value: i64) -> Lir {
+        Lir::Int(value.into())
+    }
+
+    fn path(path: usize) -> Lir {
+        Lir::Path(path)
+    }
+
+    fn branch(condition: Lir, then_branch: Lir, else_branch: Lir) -> Lir {
+        Lir::If {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        }
+    }
+
+    fn lt(lhs: Lir, rhs: Lir) -> Lir {
+        Lir::Lt(Box::new(lhs), Box::new(rhs))
+    }
+
+    #[test]
+    fn swap_less_than() {
+        assert_eq!(
+            select(lt(path(2), path(5))),
+            Clvm::Op(Opcode::Gt, vec![Clvm::Path(5), Clvm::Path(2)])
+        );
+    }
+
+    #[test]
+    fn boolean_branches() {
+        let gt = Clvm::Op(Opcode::Gt, vec![Clvm::Path(5), Clvm::Path(2)]);
+
+        assert_eq!(select(branch(lt(path(2), path(5)), int(1), int(0))), gt);
+        assert_eq!(
+            select(branch(lt(path(2), path(5)), int(0), int(1))),
+            Clvm::Op(Opcode::Not, vec![gt.clone()])
+        );
+        assert_eq!(
+            select(branch(
+                branch(lt(path(2), path(5)), int(0), int(1)),
+                int(0),
+                int(1)
+            )),
+            gt
+        );
+    }
+
+    #[test]
+    fn fold_negated_conditions() {
+        let negated = branch(path(2), int(0), int(1));
+        assert_eq!(
+            select(branch(negated, path(5), path(11))),
+            select(branch(path(2), path(11), path(5)))
+        );
+    }
+
+    #[test]
+    fn eager_constant_branches() {
+        assert_eq!(
+            select(branch(path(2), int(10), Lir::String("x".to_string()))),
+            Clvm::Op(
+                Opcode::If,
+                vec![
+                    Clvm::Path(2),
+                    Clvm::Int(10.into()),
+                    Clvm::Bytes(b"x".to_vec())
+                ]
+            )
+        );
+        assert!(matches!(
+            select(branch(path(2), path(5), int(1))),
+            Clvm::Op(Opcode::Apply, _)
+        ));
+    }
+}
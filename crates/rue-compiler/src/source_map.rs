@@ -0,0 +1,107 @@
+use std::collections::{BTreeMap, HashMap};
+
+use clvmr::{
+    allocator::{NodePtr, SExp},
+    Allocator,
+};
+use num_bigint::BigUint;
+use num_traits::One;
+use rue_error::TextRange;
+
+use crate::select::Clvm;
+
+/// Maps nodes in a compiled program back to the source ranges they were compiled from.
+///
+/// Nodes are identified by their path from the root of the program, which is encoded the
+/// same way as a path into the environment. Only expressions that can fail at runtime,
+/// such as operators and function calls, are included.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    ranges: BTreeMap<BigUint, TextRange>,
+}
+
+impl SourceMap {
+    pub(crate) fn new(clvm: &Clvm) -> Self {
+        let mut source_map = Self::default();
+        source_map.visit(clvm, BigUint::one());
+        source_map
+    }
+
+    /// The source range of the node at the given path, if there is one.
+    pub fn get(&self, path: &BigUint) -> Option<TextRange> {
+        self.ranges.get(path).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Writes the source map as text, with one line per node in the form `path file:range`.
+    pub fn to_text(&self, file: &str) -> String {
+        self.ranges
+            .iter()
+            .map(|(path, range)| format!("{path} {file}:{range}\n"))
+            .collect()
+    }
+
+    /// Finds the source range of each node in a program that was read from its
+    /// serialized form, so that they can be looked up while it's running.
+    pub fn resolve(&self, allocator: &Allocator, program: NodePtr) -> HashMap<NodePtr, TextRange> {
+        let mut ranges = HashMap::new();
+        let mut stack = vec![(program, BigUint::one())];
+
+        while let Some((node, path)) = stack.pop() {
+            if let Some(range) = self.get(&path) {
+                ranges.entry(node).or_insert(range);
+            }
+            if let SExp::Pair(first, rest) = allocator.sexp(node) {
+                stack.push((rest, rest_path(&path)));
+                stack.push((first, first_path(&path)));
+            }
+        }
+
+        ranges
+    }
+
+    /// Records the ranges in the same shape that the compiler allocates the program in.
+    /// Inner ranges are more specific, so they replace any outer range at the same path.
+    fn visit(&mut self, clvm: &Clvm, path: BigUint) {
+        match clvm {
+            Clvm::Nil | Clvm::Path(_) | Clvm::Int(_) | Clvm::Bytes(_) => {}
+            Clvm::Program(value) => self.visit(value, rest_path(&path)),
+            Clvm::Op(_, args) => {
+                let mut list = path;
+                for arg in args {
+                    list = rest_path(&list);
+                    self.visit(arg, first_path(&list));
+                }
+            }
+            Clvm::Span(range, value) => {
+                self.ranges.insert(path.clone(), *range);
+                self.visit(value, path);
+            }
+        }
+    }
+}
+
+fn first_path(path: &BigUint) -> BigUint {
+    path + (BigUint::one() << (path.bits() - 1))
+}
+
+fn rest_path(path: &BigUint) -> BigUint {
+    path + (BigUint::from(2u8) << (path.bits() - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths() {
+        let one = BigUint::one();
+        assert_eq!(first_path(&one), 2u8.into());
+        assert_eq!(rest_path(&one), 3u8.into());
+        assert_eq!(first_path(&rest_path(&one)), 5u8.into());
+        assert_eq!(rest_path(&first_path(&one)), 6u8.into());
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use clvmr::{
+    allocator::{NodePtr, SExp},
+    sha2::{Digest, Sha256},
+    Allocator,
+};
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+/// The sha256 tree hash of a CLVM value, which is how puzzles are identified on chain.
+pub type TreeHash = [u8; 32];
+
+/// Hashes an atom, which is prefixed with `1` to distinguish it from a pair.
+pub fn tree_hash_atom(atom: &[u8]) -> TreeHash {
+    let mut hasher = Sha256::new();
+    hasher.update([1]);
+    hasher.update(atom);
+    hasher.finalize().into()
+}
+
+/// Hashes a pair from the hashes of its first and rest values.
+pub fn tree_hash_pair(first: &TreeHash, rest: &TreeHash) -> TreeHash {
+    let mut hasher = Sha256::new();
+    hasher.update([2]);
+    hasher.update(first);
+    hasher.update(rest);
+    hasher.finalize().into()
+}
+
+/// Computes the tree hash of a value.
+///
+/// Each node is only hashed once, so subtrees which are shared in the allocator
+/// don't need to be hashed again.
+pub fn tree_hash(allocator: &Allocator, node: NodePtr) -> TreeHash {
+    let mut hashes = HashMap::new();
+    let mut stack = vec![node];
+
+    while let Some(&node) = stack.last() {
+        if hashes.contains_key(&node) {
+            stack.pop();
+            continue;
+        }
+
+        match allocator.sexp(node) {
+            SExp::Atom => {
+                hashes.insert(node, tree_hash_atom(allocator.atom(node)));
+                stack.pop();
+            }
+            SExp::Pair(first, rest) => match (hashes.get(&first), hashes.get(&rest)) {
+                (Some(first), Some(rest)) => {
+                    let hash = tree_hash_pair(first, rest);
+                    hashes.insert(node, hash);
+                    stack.pop();
+                }
+                _ => {
+                    stack.push(first);
+                    stack.push(rest);
+                }
+            },
+        }
+    }
+
+    hashes[&node]
+}
+
+/// A value that can be curried into a puzzle, without having to allocate it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CurriedValue {
+    Int(BigInt),
+    Bytes(Vec<u8>),
+    /// A value that has already been hashed, such as another puzzle.
+    TreeHash(TreeHash),
+    /// A nil terminated list of values.
+    List(Vec<CurriedValue>),
+}
+
+impl CurriedValue {
+    pub fn tree_hash(&self) -> TreeHash {
+        match self {
+            Self::Int(value) if value.is_zero() => tree_hash_atom(&[]),
+            Self::Int(value) => tree_hash_atom(&value.to_signed_bytes_be()),
+            Self::Bytes(value) => tree_hash_atom(value),
+            Self::TreeHash(hash) => *hash,
+            Self::List(items) => items.iter().rev().fold(tree_hash_atom(&[]), |rest, item| {
+                tree_hash_pair(&item.tree_hash(), &rest)
+            }),
+        }
+    }
+}
+
+/// Computes the hash of a puzzle after currying in arguments, given only the hash of the
+/// original puzzle.
+///
+/// The curried puzzle is `(a (q . PUZZLE) (c (q . ARG1) (c (q . ARG2) 1)))`, which is the
+/// same form that the standard `curry` produces.
+pub fn curry_tree_hash(puzzle_hash: &TreeHash, args: &[CurriedValue]) -> TreeHash {
+    let nil = tree_hash_atom(&[]);
+    let op_q = tree_hash_atom(&[1]);
+    let op_a = tree_hash_atom(&[2]);
+    let op_c = tree_hash_atom(&[4]);
+
+    let quote = |hash: &TreeHash| tree_hash_pair(&op_q, hash);
+    let list = |items: &[TreeHash]| {
+        items
+            .iter()
+            .rev()
+            .fold(nil, |rest, item| tree_hash_pair(item, &rest))
+    };
+
+    let environment = args.iter().rev().fold(tree_hash_atom(&[1]), |rest, arg| {
+        list(&[op_c, quote(&arg.tree_hash()), rest])
+    });
+
+    list(&[op_a, quote(puzzle_hash), environment])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assemble;
+
+    use super::*;
+
+    fn hash_of(text: &str) -> TreeHash {
+        let mut allocator = Allocator::new();
+        let node = assemble(&mut allocator, text).unwrap();
+        tree_hash(&allocator, node)
+    }
+
+    fn hex(hash: TreeHash) -> String {
+        hash.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn known_hashes() {
+        assert_eq!(
+            hex(hash_of("()")),
+            "4bf5122f344554c53bde2ebb8cd2b7e3d1600ad631c385a5d7cce23c7785459a"
+        );
+        assert_eq!(
+            hex(hash_of("1")),
+            "9dcf97a184f32623d11a73124ceb99a5709b083721e878a16d78f596718ba7b2"
+        );
+    }
+
+    #[test]
+    fn pairs_hash_their_children() {
+        assert_eq!(
+            hash_of("(1 . 2)"),
+            tree_hash_pair(&tree_hash_atom(&[1]), &tree_hash_atom(&[2]))
+        );
+    }
+
+    #[test]
+    fn curried_hash_matches_curried_program() {
+        let puzzle = "(+ 2 5)";
+        let curried = "(a (q . (+ 2 5)) (c (q . 42) (c (q . \"hello\") (c (q . (1 2)) 1))))";
+
+        let args = [
+            CurriedValue::Int(42.into()),
+            CurriedValue::Bytes(b"hello".to_vec()),
+            CurriedValue::List(vec![
+                CurriedValue::Int(1.into()),
+                CurriedValue::TreeHash(hash_of("2")),
+            ]),
+        ];
+
+        assert_eq!(curry_tree_hash(&hash_of(puzzle), &args), hash_of(curried));
+        assert_eq!(
+            curry_tree_hash(&hash_of(puzzle), &[]),
+            hash_of("(a (q . (+ 2 5)) 1)")
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use std::iter;
+
+use rowan::ast::AstNode;
+use rue_ast::Program;
+use rue_compiler::Compiler;
+use rue_parser::parse_text;
+
+pub use rue_compiler::{SourceMap, TreeHash};
+pub use rue_error::{Code, Diagnostic, Severity};
+pub use rue_hir::Test;
+pub use rue_lir::{CompileError, CompileErrorKind, Lir, OptLevel, Pass, PassManager};
+
+/// How a program is compiled. The defaults compile `main` with the passes of the
+/// default optimization level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileOptions {
+    pub passes: PassManager,
+    /// The function that the program runs, which is usually `main`.
+    pub entry: String,
+    /// Functions that other programs are compiled from, such as the other targets of a
+    /// project. Functions that only they use aren't reported as unused.
+    pub other_entries: Vec<String>,
+    /// Serializes the program with back-references to repeated subtrees, which is
+    /// usually smaller but can only be read by deserializers that support them.
+    pub backrefs: bool,
+    /// Keeps the high-level intermediate representation of each function.
+    pub emit_hir: bool,
+    /// Keeps the optimized low-level intermediate representation of the program.
+    pub emit_lir: bool,
+    /// Stops once the program has been lowered, without compiling it to CLVM. Errors such
+    /// as a missing entry function are still reported.
+    pub check_only: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            passes: PassManager::new(OptLevel::default()),
+            entry: "main".to_string(),
+            other_entries: Vec::new(),
+            backrefs: false,
+            emit_hir: false,
+            emit_lir: false,
+            check_only: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileOutput {
+    /// The errors and warnings in the source. Errors come first, and the program is
+    /// only compiled if there aren't any.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The compiled program, unless [`CompileOptions::check_only`] was set.
+    pub program: Option<CompiledProgram>,
+    /// Why the program couldn't be compiled even though the source has no errors, such
+    /// as when there is no entry function.
+    pub error: Option<CompileError>,
+    /// The names of the parameters of `main` which are curried into the program, in the
+    /// order that their values have to be given.
+    pub curried_parameters: Vec<String>,
+    /// The functions marked with `#[test]`, each of which can be compiled into its own
+    /// program by setting [`CompileOptions::entry`] to its name.
+    pub tests: Vec<(String, Test)>,
+    /// The high-level intermediate representation, if [`CompileOptions::emit_hir`] was set.
+    /// It is available as long as the source could be parsed, even if there were errors.
+    pub hir: Option<String>,
+}
+
+impl CompileOutput {
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity() == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity() != Severity::Error)
+    }
+}
+
+/// A program that was compiled without errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledProgram {
+    /// The serialized program.
+    pub bytes: Vec<u8>,
+    /// The size of the program without back-references, if [`CompileOptions::backrefs`]
+    /// was set, so that the savings can be reported.
+    pub size_without_backrefs: Option<usize>,
+    /// The sha256 tree hash of the program, which identifies it as a puzzle.
+    pub hash: TreeHash,
+    /// Maps each node path in the program to the range in the source that it was
+    /// compiled from.
+    pub source_map: SourceMap,
+    /// The low-level intermediate representation, if [`CompileOptions::emit_lir`] was set.
+    pub lir: Option<Lir>,
+}
+
+/// Compiles the source of a program into CLVM, running every stage of the compiler.
+pub fn compile(source: &str, options: &CompileOptions) -> CompileOutput {
+    let mut output = CompileOutput {
+        diagnostics: Vec::new(),
+        program: None,
+        error: None,
+        curried_parameters: Vec::new(),
+        tests: Vec::new(),
+        hir: None,
+    };
+
+    let (parser_errors, node) = parse_text(source);
+    output.diagnostics.extend(parser_errors);
+
+    let Some(program) = Program::cast(node) else {
+        return output;
+    };
+
+    let entry_points = iter::once(&options.entry)
+        .chain(&options.other_entries)
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    let rue_hir::Output {
+        errors,
+        warnings,
+        db,
+        scope,
+    } = rue_hir::lower_with_entry_points(program, &entry_points);
+    output.diagnostics.extend(errors);
+    output.diagnostics.extend(warnings);
+
+    output.curried_parameters = rue_hir::curried_parameters(&db, &scope);
+    output.tests = rue_hir::test_functions(&db, &scope)
+        .into_iter()
+        .map(|(name, _, test)| (name, test))
+        .collect();
+    if options.emit_hir {
+        output.hir = Some(rue_hir::dump_hir(&db, &scope));
+    }
+
+    if output.errors().next().is_some() {
+        return output;
+    }
+
+    let result = scope
+        .lookup_symbol(&options.entry)
+        .ok_or_else(|| CompileErrorKind::MissingEntry(options.entry.clone()).into())
+        .and_then(|entry| rue_lir::lower_entry(db, &scope, entry, &options.passes))
+        .and_then(|lir| {
+            if options.check_only {
+                return Ok(None);
+            }
+            compile_lir(options.passes.run(lir), options).map(Some)
+        });
+
+    match result {
+        Ok(program) => output.program = program,
+        Err(error) => output.error = Some(error),
+    }
+
+    output
+}
+
+fn compile_lir(lir: Lir, options: &CompileOptions) -> Result<CompiledProgram, CompileError> {
+    let emitted_lir = options.emit_lir.then(|| lir.clone());
+    let mut compiler = Compiler::new();
+    let (ptr, source_map) = compiler.compile_to_node(lir)?;
+
+    let mut bytes = compiler.serialize(ptr)?;
+    let mut size_without_backrefs = None;
+    if options.backrefs {
+        size_without_backrefs = Some(bytes.len());
+        bytes = compiler.serialize_compact(ptr)?;
+    }
+
+    Ok(CompiledProgram {
+        bytes,
+        size_without_backrefs,
+        hash: compiler.tree_hash(ptr),
+        source_map,
+        lir: emitted_lir,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_program() {
+        let output = compile(
+            "fn main(curry base: Int, value: Int) -> Int { base + value }",
+            &CompileOptions::default(),
+        );
+        assert_eq!(output.diagnostics, Vec::new());
+        assert_eq!(output.curried_parameters, vec!["base"]);
+        assert_eq!(output.hir, None);
+
+        let program = output.program.unwrap();
+        assert_eq!(
+            program.bytes,
+            vec![0xff, 0x10, 0xff, 0x02, 0xff, 0x05, 0x80]
+        );
+        assert_eq!(
+            program.hash,
+            rue_compiler::tree_hash_pair(
+                &rue_compiler::tree_hash_atom(&[0x10]),
+                &rue_compiler::tree_hash_pair(
+                    &rue_compiler::tree_hash_atom(&[0x02]),
+                    &rue_compiler::tree_hash_pair(
+                        &rue_compiler::tree_hash_atom(&[0x05]),
+                        &rue_compiler::tree_hash_atom(&[]),
+                    ),
+                ),
+            )
+        );
+        assert_eq!(program.lir, None);
+        assert_eq!(program.size_without_backrefs, None);
+    }
+
+    #[test]
+    fn compile_with_options() {
+        let source = "fn helper() -> Int { 40 + 2 }\nfn main() -> Int { 0 }";
+        let options = CompileOptions {
+            passes: PassManager::new(OptLevel::O0),
+            entry: "helper".to_string(),
+            emit_hir: true,
+            emit_lir: true,
+            ..Default::default()
+        };
+
+        let output = compile(source, &options);
+        assert_eq!(output.warnings().count(), 0);
+        assert!(output.hir.unwrap().contains("fn helper"));
+
+        let program = output.program.unwrap();
+        assert!(program.lir.is_some());
+        assert_eq!(
+            program.bytes,
+            vec![0xff, 0x10, 0xff, 0xff, 0x01, 0x28, 0xff, 0xff, 0x01, 0x02, 0x80]
+        );
+    }
+
+    #[test]
+    fn compile_each_entry() {
+        let source = "fn first() -> Int { helper(1) }
+            fn second() -> Int { helper(2) }
+            fn helper(value: Int) -> Int { value * 2 }
+            #[test] fn doubles() -> Int { if helper(2) > 3 { 1 } else { 1 / 0 } }";
+
+        let options = CompileOptions {
+            entry: "first".to_string(),
+            other_entries: vec!["second".to_string()],
+            backrefs: true,
+            ..Default::default()
+        };
+
+        let output = compile(source, &options);
+        assert_eq!(output.diagnostics, Vec::new());
+        assert_eq!(
+            output.tests,
+            vec![("doubles".to_string(), Test { should_fail: false })]
+        );
+
+        let program = output.program.unwrap();
+        assert!(program.size_without_backrefs.unwrap() >= program.bytes.len());
+
+        let test = compile(
+            source,
+            &CompileOptions {
+                entry: "doubles".to_string(),
+                ..options
+            },
+        );
+        assert!(test.program.is_some());
+    }
+
+    #[test]
+    fn errors_stop_compilation() {
+        let options = CompileOptions {
+            emit_hir: true,
+            ..Default::default()
+        };
+
+        let output = compile(
+            "fn main() -> Int { \"text\" }\nfn unused() -> Int { 1 }",
+            &options,
+        );
+        assert_eq!(
+            output
+                .errors()
+                .map(|error| error.code())
+                .collect::<Vec<_>>(),
+            vec![Code::ReturnTypeMismatch]
+        );
+        assert_eq!(
+            output
+                .warnings()
+                .map(|warning| warning.code())
+                .collect::<Vec<_>>(),
+            vec![Code::Unused]
+        );
+        assert_eq!(output.program, None);
+        assert_eq!(output.error, None);
+        assert!(output.hir.is_some());
+    }
+
+    #[test]
+    fn missing_entry() {
+        let output = compile("fn helper() -> Int { 1 }", &CompileOptions::default());
+        assert_eq!(output.diagnostics, Vec::new());
+        assert_eq!(output.program, None);
+        assert_eq!(
+            output.error.map(|error| error.kind),
+            Some(CompileErrorKind::MissingEntry("main".to_string()))
+        );
+    }
+
+    #[test]
+    fn check_only() {
+        let options = CompileOptions {
+            check_only: true,
+            ..Default::default()
+        };
+
+        let output = compile("fn main() -> Int { 42 }", &options);
+        assert_eq!(output.diagnostics, Vec::new());
+        assert_eq!(output.program, None);
+        assert_eq!(output.error, None);
+
+        let output = compile("fn helper() -> Int { 1 }", &options);
+        assert_eq!(
+            output.error.map(|error| error.kind),
+            Some(CompileErrorKind::MissingEntry("main".to_string()))
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+macro_rules! codes {
+    ($( $(#[$meta:meta])* $name:ident = $code:literal, )+) => {
+        /// A stable code for each kind of diagnostic, so they can be looked up and
+        /// documented even if the wording of the message changes.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum Code {
+            $( $(#[$meta])* $name, )+
+        }
+
+        impl Code {
+            /// Every code, in order.
+            pub const ALL: &'static [Code] = &[$( Self::$name, )+];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $( Self::$name => $code, )+
+                }
+            }
+
+            /// Looks up a code by its text, such as `E0106`.
+            pub fn parse(text: &str) -> Option<Self> {
+                match text {
+                    $( $code => Some(Self::$name), )+
+                    _ => None,
+                }
+            }
+
+            /// A long-form explanation of the code in Markdown, with an example of code
+            /// that causes it and how to fix it.
+            pub fn explanation(self) -> &'static str {
+                match self {
+                    $( Self::$name => include_str!(concat!("../explanations/", $code, ".md")), )+
+                }
+            }
+        }
+    };
+}
+
+codes! {
+    /// A character that isn't part of any token.
+    UnknownToken = "E0001",
+    UnterminatedBlockComment = "E0002",
+    UnterminatedString = "E0003",
+    /// A token that the grammar doesn't allow at this point.
+    UnexpectedToken = "E0004",
+    ExpectedItem = "E0005",
+    ExpectedStatement = "E0006",
+    ExpectedExpression = "E0007",
+    ExpectedType = "E0008",
+
+    CurryOutsideMain = "E0101",
+    CurryAfterParameter = "E0102",
+    ReturnTypeMismatch = "E0103",
+    AssignTypeMismatch = "E0104",
+    InvalidInteger = "E0105",
+    UndefinedVariable = "E0106",
+    BuiltinAsValue = "E0107",
+    OperatorTypeMismatch = "E0108",
+    NotCallable = "E0109",
+    ArgumentCount = "E0110",
+    ArgumentTypeMismatch = "E0111",
+    ListItemTypeMismatch = "E0112",
+    BranchTypeMismatch = "E0113",
+    UndefinedType = "E0114",
+    DuplicateDefinition = "E0115",
+    UnknownAttribute = "E0116",
+    ShouldFailWithoutTest = "E0117",
+    TestParameters = "E0118",
+
+    /// A function, parameter or variable that is never used.
+    Unused = "W0001",
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn codes_are_unique_and_parse() {
+        let mut seen = HashSet::new();
+        for code in Code::ALL {
+            assert!(seen.insert(code.as_str()), "duplicate code {code}");
+            assert_eq!(Code::parse(code.as_str()), Some(*code));
+        }
+        assert_eq!(Code::parse("E9999"), None);
+    }
+
+    #[test]
+    fn every_code_is_explained() {
+        for code in Code::ALL {
+            let explanation = code.explanation();
+            assert!(
+                explanation.contains("```rue,compile_fail")
+                    || explanation.contains("```rue,ignore"),
+                "{code} has no erroneous example"
+            );
+            assert!(
+                explanation.contains("```rue\n"),
+                "{code} has no corrected example"
+            );
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{error, fmt};
+
+use crate::{Code, TextRange};
+
+/// How serious a diagnostic is. Only errors stop a program from being compiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Hint,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+            Self::Hint => write!(f, "hint"),
+        }
+    }
+}
+
+/// A range of the source that a diagnostic refers to, with an optional explanation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub range: TextRange,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NoteKind {
+    /// Extra context about why the diagnostic happened.
+    Note,
+    /// A suggestion for how to fix it.
+    Help,
+}
+
+impl fmt::Display for NoteKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Note => write!(f, "note"),
+            Self::Help => write!(f, "help"),
+        }
+    }
+}
+
+/// A change to the source which fixes the diagnostic, that tools can apply automatically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub range: TextRange,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    pub kind: NoteKind,
+    pub message: String,
+    pub suggestion: Option<Suggestion>,
+}
+
+/// A problem found in the source, such as a syntax error or an unused variable.
+///
+/// The primary label is where the problem is, and secondary labels point at related
+/// code, such as where something was first defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: Code,
+    message: String,
+    primary: Label,
+    secondary: Vec<Label>,
+    notes: Vec<Note>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: Code, message: String, range: TextRange) -> Self {
+        Self {
+            severity,
+            code,
+            message,
+            primary: Label {
+                range,
+                message: None,
+            },
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(code: Code, message: String, range: TextRange) -> Self {
+        Self::new(Severity::Error, code, message, range)
+    }
+
+    pub fn warning(code: Code, message: String, range: TextRange) -> Self {
+        Self::new(Severity::Warning, code, message, range)
+    }
+
+    /// Explains the primary range, which is shown next to it.
+    pub fn with_label(mut self, message: String) -> Self {
+        self.primary.message = Some(message);
+        self
+    }
+
+    pub fn with_secondary(mut self, range: TextRange, message: String) -> Self {
+        self.secondary.push(Label {
+            range,
+            message: Some(message),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, message: String) -> Self {
+        self.notes.push(Note {
+            kind: NoteKind::Note,
+            message,
+            suggestion: None,
+        });
+        self
+    }
+
+    pub fn with_help(mut self, message: String) -> Self {
+        self.notes.push(Note {
+            kind: NoteKind::Help,
+            message,
+            suggestion: None,
+        });
+        self
+    }
+
+    /// Adds a help note which replaces a range of the source to fix the diagnostic.
+    pub fn with_suggestion(
+        mut self,
+        message: String,
+        range: TextRange,
+        replacement: String,
+    ) -> Self {
+        self.notes.push(Note {
+            kind: NoteKind::Help,
+            message,
+            suggestion: Some(Suggestion { range, replacement }),
+        });
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The range of the primary label.
+    pub fn range(&self) -> TextRange {
+        self.primary.range
+    }
+
+    pub fn primary(&self) -> &Label {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &[Label] {
+        &self.secondary
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.primary.range)
+    }
+}
+
+impl error::Error for Diagnostic {}
@@ -1,27 +1,13 @@
-use std::{error, fmt};
-
+mod code;
+mod diagnostic;
+mod line_index;
+mod render;
 mod text_pos;
 mod text_range;
 
+pub use code::*;
+pub use diagnostic::*;
+pub use line_index::*;
+pub use render::*;
 pub use text_pos::*;
 pub use text_range::*;
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Error {
-    message: String,
-    range: TextRange,
-}
-
-impl Error {
-    pub fn new(message: String, range: TextRange) -> Self {
-        Self { message, range }
-    }
-}
-
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} at {}", self.message, self.range)
-    }
-}
-
-impl error::Error for Error {}
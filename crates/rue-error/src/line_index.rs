@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use crate::{TextPos, TextRange};
+
+/// A zero-based line and column. The column is measured in bytes, or in UTF-16 code
+/// units when it was converted with [`LineIndex::utf16_line_col`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+impl LineCol {
+    pub fn new(line: u32, col: u32) -> Self {
+        Self { line, col }
+    }
+}
+
+/// A character which is more than one byte long, and where it is in its line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WideChar {
+    start: u32,
+    len_utf8: u32,
+    len_utf16: u32,
+}
+
+/// Converts between byte positions in a file and lines and columns.
+///
+/// Only the start of each line and the characters that aren't ASCII are stored,
+/// so the source doesn't have to be kept around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    line_starts: Vec<u32>,
+    len: u32,
+    wide_chars: HashMap<u32, Vec<WideChar>>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars = HashMap::new();
+        let mut line_wide_chars = Vec::new();
+        let mut line_start = 0;
+
+        for (pos, c) in text.char_indices() {
+            let pos = pos as u32;
+
+            if c == '\n' {
+                if !line_wide_chars.is_empty() {
+                    wide_chars.insert(line_starts.len() as u32 - 1, line_wide_chars);
+                    line_wide_chars = Vec::new();
+                }
+                line_start = pos + 1;
+                line_starts.push(line_start);
+            } else if !c.is_ascii() {
+                line_wide_chars.push(WideChar {
+                    start: pos - line_start,
+                    len_utf8: c.len_utf8() as u32,
+                    len_utf16: c.len_utf16() as u32,
+                });
+            }
+        }
+
+        if !line_wide_chars.is_empty() {
+            wide_chars.insert(line_starts.len() as u32 - 1, line_wide_chars);
+        }
+
+        Self {
+            line_starts,
+            len: text.len() as u32,
+            wide_chars,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The line and byte column of a position. Positions past the end of the text are
+    /// treated as the end of the text.
+    pub fn line_col(&self, pos: TextPos) -> LineCol {
+        let pos = (pos.pos() as u32).min(self.len);
+        let line = self.line_starts.partition_point(|start| *start <= pos) - 1;
+        LineCol::new(line as u32, pos - self.line_starts[line])
+    }
+
+    /// The byte position of a line and byte column, if it is in the text.
+    pub fn offset(&self, line_col: LineCol) -> Option<TextPos> {
+        let range = self.line_range(line_col.line)?;
+        let pos = range.from().pos() + line_col.col as usize;
+        (pos <= range.to().pos()).then(|| pos.into())
+    }
+
+    /// The line and column of a position in UTF-16 code units, which is what editors
+    /// using the language server protocol expect.
+    pub fn utf16_line_col(&self, pos: TextPos) -> LineCol {
+        let LineCol { line, col } = self.line_col(pos);
+        let mut utf16_col = col;
+
+        for c in self.wide_chars(line) {
+            if c.start >= col {
+                break;
+            }
+            utf16_col -= c.len_utf8 - c.len_utf16;
+        }
+
+        LineCol::new(line, utf16_col)
+    }
+
+    /// The byte position of a line and column in UTF-16 code units.
+    pub fn utf16_offset(&self, line_col: LineCol) -> Option<TextPos> {
+        // The number of bytes more than UTF-16 units in the line so far.
+        let mut extra = 0;
+
+        for c in self.wide_chars(line_col.line) {
+            if c.start - extra >= line_col.col {
+                break;
+            }
+            extra += c.len_utf8 - c.len_utf16;
+        }
+
+        self.offset(LineCol::new(line_col.line, line_col.col + extra))
+    }
+
+    /// The range of a line, not including the line break at the end of it.
+    pub fn line_range(&self, line: u32) -> Option<TextRange> {
+        let start = *self.line_starts.get(line as usize)?;
+        let end = match self.line_starts.get(line as usize + 1) {
+            Some(next) => next - 1,
+            None => self.len,
+        };
+        Some((start as usize..end as usize).into())
+    }
+
+    fn wide_chars(&self, line: u32) -> impl Iterator<Item = &WideChar> {
+        self.wide_chars.get(&line).into_iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_and_columns() {
+        let index = LineIndex::new("fn main()\n  -> Int\n\n{ 42 }");
+        assert_eq!(index.line_count(), 4);
+
+        let cases = [(0, (0, 0)), (3, (0, 3)), (9, (0, 9)), (10, (1, 0))];
+        for (pos, (line, col)) in cases {
+            assert_eq!(index.line_col(pos.into()), LineCol::new(line, col));
+            assert_eq!(index.offset(LineCol::new(line, col)), Some(pos.into()));
+        }
+
+        assert_eq!(index.line_col(20.into()), LineCol::new(3, 0));
+        assert_eq!(index.line_col(100.into()), LineCol::new(3, 6));
+        assert_eq!(index.offset(LineCol::new(0, 10)), None);
+        assert_eq!(index.offset(LineCol::new(4, 0)), None);
+
+        assert_eq!(index.line_range(1), Some((10..18).into()));
+        assert_eq!(index.line_range(2), Some((19..19).into()));
+        assert_eq!(index.line_range(3), Some((20..26).into()));
+    }
+
+    #[test]
+    fn utf16_columns() {
+        // `é` is two bytes and one UTF-16 unit, and `𝕏` is four bytes and two units.
+        let text = "x\n\"é𝕏\" + y";
+        let index = LineIndex::new(text);
+
+        let y = text.find('y').unwrap();
+        assert_eq!(index.line_col(y.into()), LineCol::new(1, 11));
+        assert_eq!(index.utf16_line_col(y.into()), LineCol::new(1, 8));
+        assert_eq!(index.utf16_offset(LineCol::new(1, 8)), Some(y.into()));
+
+        let quote = text.rfind('"').unwrap();
+        assert_eq!(index.utf16_line_col(quote.into()), LineCol::new(1, 4));
+        assert_eq!(index.utf16_offset(LineCol::new(1, 4)), Some(quote.into()));
+        assert_eq!(index.utf16_offset(LineCol::new(1, 2)), Some(5.into()));
+        assert_eq!(index.utf16_line_col(1.into()), LineCol::new(0, 1));
+    }
+}
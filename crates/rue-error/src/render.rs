@@ -0,0 +1,296 @@
+use std::{fmt::Write, ops::Range};
+
+use itertools::Itertools;
+
+use crate::{Diagnostic, LineIndex, Severity, TextRange};
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+fn severity_color(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => RED,
+        Severity::Warning => YELLOW,
+        Severity::Hint => CYAN,
+    }
+}
+
+/// A range to underline, after whitespace has been trimmed from it.
+struct Underline<'a> {
+    range: TextRange,
+    message: Option<&'a str>,
+    is_primary: bool,
+}
+
+/// Renders diagnostics for a single file, with the lines they are on and carets under
+/// the parts of them that they refer to.
+pub struct Renderer<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    line_index: LineIndex,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(file_name: &'a str, source: &'a str) -> Self {
+        Self {
+            file_name,
+            source,
+            line_index: LineIndex::new(source),
+            color: false,
+        }
+    }
+
+    /// Uses ANSI escape codes to color the output, which is only useful for terminals.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let severity = diagnostic.severity();
+        let mut underlines = vec![Underline {
+            range: diagnostic.range().trim_whitespace(self.source),
+            message: diagnostic.primary().message.as_deref(),
+            is_primary: true,
+        }];
+        for label in diagnostic.secondary() {
+            underlines.push(Underline {
+                range: label.range.trim_whitespace(self.source),
+                message: label.message.as_deref(),
+                is_primary: false,
+            });
+        }
+
+        let mut output = format!(
+            "{}{}\n",
+            self.paint(
+                &format!("{severity}[{}]", diagnostic.code()),
+                severity_color(severity)
+            ),
+            self.paint(&format!(": {}", diagnostic.message()), BOLD),
+        );
+        output.push_str(&self.render_underlines(severity, &underlines));
+
+        let gutter = self.gutter(&underlines);
+        for note in diagnostic.notes() {
+            writeln!(
+                output,
+                "{gutter} {} {}: {}",
+                self.paint("=", BLUE),
+                self.paint(&note.kind.to_string(), BOLD),
+                note.message
+            )
+            .unwrap();
+        }
+
+        output
+    }
+
+    /// Renders where a range is and the line it's on, without a message.
+    pub fn render_snippet(&self, severity: Severity, range: TextRange) -> String {
+        self.render_underlines(
+            severity,
+            &[Underline {
+                range: range.trim_whitespace(self.source),
+                message: None,
+                is_primary: true,
+            }],
+        )
+    }
+
+    fn render_underlines(&self, severity: Severity, underlines: &[Underline]) -> String {
+        let gutter = self.gutter(underlines);
+        let start = self.line_index.line_col(underlines[0].range.from());
+
+        let mut output = String::new();
+        writeln!(
+            output,
+            "{gutter}{} {}:{}:{}",
+            self.paint("-->", BLUE),
+            self.file_name,
+            start.line + 1,
+            start.col + 1
+        )
+        .unwrap();
+        writeln!(output, "{gutter} {}", self.paint("|", BLUE)).unwrap();
+
+        // Labels on the same line share it, and lines are shown in the order of the source.
+        let lines = underlines
+            .iter()
+            .into_group_map_by(|underline| self.line_index.line_col(underline.range.from()).line)
+            .into_iter()
+            .sorted_by_key(|(line, _)| *line);
+
+        let mut previous_line = None;
+
+        for (line, underlines) in lines {
+            if previous_line.is_some_and(|previous| previous + 1 < line) {
+                writeln!(output, "{}", self.paint("...", BLUE)).unwrap();
+            }
+            previous_line = Some(line);
+
+            let line_range = self.line_index.line_range(line).unwrap();
+            let text = &self.source[Range::from(line_range)];
+            let line_number = format!("{:>width$} |", line + 1, width = gutter.len());
+            writeln!(output, "{} {text}", self.paint(&line_number, BLUE)).unwrap();
+
+            for underline in underlines
+                .into_iter()
+                .sorted_by_key(|underline| underline.range.from())
+            {
+                let range = underline.range;
+
+                // Ranges that span several lines are only underlined up to the end of the first.
+                let end = range.to().pos().min(line_range.to().pos());
+                let prefix = &self.source[line_range.from().pos()..range.from().pos()];
+                let highlighted = &self.source[range.from().pos()..end];
+
+                let indent: String = prefix
+                    .chars()
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+
+                let (marker, color) = if underline.is_primary {
+                    ("^", severity_color(severity))
+                } else {
+                    ("-", BLUE)
+                };
+                let mut marks = marker.repeat(highlighted.chars().count().max(1));
+                if let Some(message) = underline.message {
+                    marks = format!("{marks} {message}");
+                }
+
+                writeln!(
+                    output,
+                    "{gutter} {} {indent}{}",
+                    self.paint("|", BLUE),
+                    self.paint(&marks, color)
+                )
+                .unwrap();
+            }
+        }
+
+        output
+    }
+
+    /// Spaces as wide as the largest line number that will be shown.
+    fn gutter(&self, underlines: &[Underline]) -> String {
+        let largest = underlines
+            .iter()
+            .map(|underline| self.line_index.line_col(underline.range.from()).line + 1)
+            .max()
+            .unwrap_or(1);
+        " ".repeat(largest.to_string().len())
+    }
+
+    fn paint(&self, text: &str, color: &str) -> String {
+        if self.color {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Code;
+
+    use super::*;
+
+    #[test]
+    fn render_errors() {
+        let source = "fn main() -> Int {\n    let x = 5;\n    x + y\n}\n";
+        let renderer = Renderer::new("main.rue", source);
+
+        let y = source.find('y').unwrap();
+        let error = Diagnostic::error(
+            Code::UndefinedVariable,
+            "undefined variable `y`".to_string(),
+            (y..y + 1).into(),
+        );
+        assert_eq!(
+            renderer.render(&error),
+            "error[E0106]: undefined variable `y`
+ --> main.rue:3:9
+  |
+3 |     x + y
+  |         ^
+"
+        );
+
+        // The whitespace before `let` is part of the range, but isn't underlined.
+        let warning = Diagnostic::warning(
+            Code::Unused,
+            "unused variable `x`".to_string(),
+            (18..33).into(),
+        )
+        .with_help("prefix it with an underscore: `_x`".to_string());
+        assert_eq!(
+            renderer.render(&warning),
+            "warning[W0001]: unused variable `x`
+ --> main.rue:2:5
+  |
+2 |     let x = 5;
+  |     ^^^^^^^^^^
+  = help: prefix it with an underscore: `_x`
+"
+        );
+    }
+
+    #[test]
+    fn render_secondary_labels() {
+        let source = "fn f() -> Int { 1 }\n\n\nfn f() -> Int { 2 }";
+        let renderer = Renderer::new("main.rue", source);
+
+        let error = Diagnostic::error(
+            Code::DuplicateDefinition,
+            "there is already a variable named `f`".to_string(),
+            (25..26).into(),
+        )
+        .with_label("redefined here".to_string())
+        .with_secondary((3..4).into(), "first defined here".to_string())
+        .with_note("names must be unique".to_string());
+
+        assert_eq!(
+            renderer.render(&error),
+            "error[E0115]: there is already a variable named `f`
+ --> main.rue:4:4
+  |
+1 | fn f() -> Int { 1 }
+  |    - first defined here
+...
+4 | fn f() -> Int { 2 }
+  |    ^ redefined here
+  = note: names must be unique
+"
+        );
+    }
+
+    #[test]
+    fn render_at_the_end_of_the_file() {
+        let renderer = Renderer::new("main.rue", "fn main() -> Int {");
+        assert_eq!(
+            renderer.render_snippet(Severity::Error, (18..18).into()),
+            " --> main.rue:1:19
+  |
+1 | fn main() -> Int {
+  |                   ^
+"
+        );
+    }
+
+    #[test]
+    fn render_with_color() {
+        let renderer = Renderer::new("main.rue", "x").with_color(true);
+        let error = Diagnostic::error(Code::UndefinedVariable, "oops".to_string(), (0..1).into());
+        let output = renderer.render(&error);
+        assert!(output.starts_with("\x1b[1;31merror[E0106]\x1b[0m"));
+        assert!(output.contains("\x1b[1;31m^\x1b[0m"));
+    }
+}
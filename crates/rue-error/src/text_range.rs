@@ -15,7 +15,35 @@ impl TextRange {
     }
 
     pub fn to(&self) -> TextPos {
-        self.0
+        self.1
+    }
+
+    pub fn len(&self) -> usize {
+        self.1.pos() - self.0.pos()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, pos: TextPos) -> bool {
+        self.0 <= pos && pos < self.1
+    }
+
+    /// Removes whitespace from the edges of the range, since syntax nodes include the
+    /// whitespace that comes before them. The range is also clamped to the source.
+    pub fn trim_whitespace(&self, source: &str) -> TextRange {
+        let start = self.0.pos().min(source.len());
+        let end = self.1.pos().clamp(start, source.len());
+        let text = &source[start..end];
+
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            return (start..start).into();
+        }
+
+        let start = start + (text.len() - text.trim_start().len());
+        (start..start + trimmed.len()).into()
     }
 }
 
@@ -34,8 +62,44 @@ where
     }
 }
 
+impl From<TextRange> for Range<usize> {
+    fn from(value: TextRange) -> Self {
+        value.0.pos()..value.1.pos()
+    }
+}
+
 impl From<rowan::TextRange> for TextRange {
     fn from(value: rowan::TextRange) -> Self {
         Self(value.start().into(), value.end().into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        let range: TextRange = (3..8).into();
+        assert_eq!(range.from(), TextPos::new(3));
+        assert_eq!(range.to(), TextPos::new(8));
+        assert_eq!(range.len(), 5);
+        assert!(!range.is_empty());
+        assert!(range.contains(TextPos::new(3)));
+        assert!(!range.contains(TextPos::new(8)));
+        assert_eq!(Range::from(range), 3..8);
+    }
+
+    #[test]
+    fn trim_whitespace() {
+        let source = "let x = 5;\n    x + y  \n";
+        let range: TextRange = (10..source.len()).into();
+        assert_eq!(range.trim_whitespace(source), (15..20).into());
+
+        let blank: TextRange = (20..23).into();
+        assert_eq!(blank.trim_whitespace(source), (20..20).into());
+
+        let past_the_end: TextRange = (15..100).into();
+        assert_eq!(past_the_end.trim_whitespace(source), (15..20).into());
+    }
+}
@@ -0,0 +1,209 @@
+use rue_error::Diagnostic;
+use rue_parser::parse_text;
+use rue_syntax::{SyntaxKind, SyntaxNode, SyntaxToken, T};
+
+const INDENT: &str = "    ";
+
+/// What goes between two tokens in the formatted output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Separator {
+    None,
+    Space,
+    Newline,
+    BlankLine,
+}
+
+/// Formats a program in the standard style. Comments are kept, along with single blank
+/// lines between statements, but all other whitespace is replaced.
+///
+/// Programs with syntax errors can't be formatted, since it isn't clear what they mean.
+pub fn format(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let (errors, node) = parse_text(source);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(format_node(&node))
+}
+
+fn format_node(node: &SyntaxNode) -> String {
+    let mut output = String::new();
+    let mut indent = 0;
+    let mut previous: Option<SyntaxToken> = None;
+    let mut newlines = 0;
+
+    for token in node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        if token.kind() == SyntaxKind::Whitespace {
+            newlines += token.text().matches('\n').count();
+            continue;
+        }
+
+        if is_block_brace(&token, T!['}']) {
+            indent -= 1;
+        }
+
+        if let Some(previous) = &previous {
+            if is_block_brace(previous, T!['{']) {
+                indent += 1;
+            }
+            match separator(previous, &token, newlines) {
+                Separator::None => {}
+                Separator::Space => output.push(' '),
+                separator => {
+                    if separator == Separator::BlankLine {
+                        output.push('\n');
+                    }
+                    output.push('\n');
+                    output.push_str(&INDENT.repeat(indent));
+                }
+            }
+        }
+
+        output.push_str(token.text());
+        previous = Some(token);
+        newlines = 0;
+    }
+
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+/// Decides what goes between two tokens, given how many line breaks there were between
+/// them in the source.
+fn separator(previous: &SyntaxToken, next: &SyntaxToken, newlines: usize) -> Separator {
+    let kept_lines = match newlines {
+        0 => Separator::Space,
+        1 => Separator::Newline,
+        _ => Separator::BlankLine,
+    };
+
+    if previous.kind() == SyntaxKind::LineComment {
+        return kept_lines.max(Separator::Newline);
+    }
+
+    // Comments stay at the end of the line they were on.
+    if next.kind().is_trivia() && newlines == 0 {
+        return Separator::Space;
+    }
+
+    // Functions are always separated by a blank line.
+    if is_block_brace(previous, T!['}']) && parent_kind(previous, 2) == Some(SyntaxKind::FnItem) {
+        return Separator::BlankLine;
+    }
+
+    if previous.kind() == T![;] {
+        return kept_lines.max(Separator::Newline);
+    }
+
+    if is_block_brace(previous, T!['{'])
+        || is_block_brace(next, T!['}'])
+        || (previous.kind() == T![']'] && parent_kind(previous, 1) == Some(SyntaxKind::Attribute))
+    {
+        return Separator::Newline;
+    }
+
+    if next.kind().is_trivia() || previous.kind().is_trivia() {
+        return kept_lines;
+    }
+
+    if matches!(next.kind(), T![')'] | T![']'] | T![,] | T![:] | T![;])
+        || matches!(previous.kind(), T!['('] | T!['['] | T![#])
+        || (previous.kind() == T![-] && parent_kind(previous, 1) == Some(SyntaxKind::PrefixExpr))
+        || (next.kind() == T!['['] && parent_kind(next, 1) == Some(SyntaxKind::Attribute))
+        || (next.kind() == T!['(']
+            && matches!(
+                parent_kind(next, 1),
+                Some(SyntaxKind::CallExpr | SyntaxKind::FnParamList)
+            ))
+    {
+        return Separator::None;
+    }
+
+    Separator::Space
+}
+
+fn is_block_brace(token: &SyntaxToken, kind: SyntaxKind) -> bool {
+    token.kind() == kind && parent_kind(token, 1) == Some(SyntaxKind::Block)
+}
+
+/// The kind of the node which is a number of levels above the token.
+fn parent_kind(token: &SyntaxToken, depth: usize) -> Option<SyntaxKind> {
+    token
+        .parent_ancestors()
+        .nth(depth - 1)
+        .map(|node| node.kind())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str, expected: &str) {
+        let formatted = format(source).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), expected, "not idempotent");
+    }
+
+    #[test]
+    fn format_functions() {
+        check(
+            "#[test]  #[should_fail]fn  fails( )->Int{1/0}
+            inline fn add(a:Int,b : Int)->Int{a+b}
+            fn main(curry x:Int,list:[Int])->[Int]{let y=-x*(2+x);if y>0{list}else{[y,add(y,1)]}}",
+            "#[test]
+#[should_fail]
+fn fails() -> Int {
+    1 / 0
+}
+
+inline fn add(a: Int, b: Int) -> Int {
+    a + b
+}
+
+fn main(curry x: Int, list: [Int]) -> [Int] {
+    let y = -x * (2 + x);
+    if y > 0 {
+        list
+    } else {
+        [y, add(y, 1)]
+    }
+}
+",
+        );
+    }
+
+    #[test]
+    fn format_comments() {
+        check(
+            "// The entry point.
+fn main() -> Int { // trailing
+    /* before */ let x = 1;
+
+
+    // Blank lines between statements are kept, but only one.
+    x
+}
+// The end.",
+            "// The entry point.
+fn main() -> Int { // trailing
+    /* before */ let x = 1;
+
+    // Blank lines between statements are kept, but only one.
+    x
+}
+
+// The end.
+",
+        );
+    }
+
+    #[test]
+    fn syntax_errors_are_not_formatted() {
+        assert!(format("fn main() -> Int { 1 + }").is_err());
+        assert_eq!(format("").unwrap(), "");
+    }
+}
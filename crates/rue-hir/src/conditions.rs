@@ -0,0 +1,75 @@
+use crate::{ty::Type, Database, Hir, Scope, Symbol};
+
+/// A builtin constructor for a condition, along with the types of its arguments.
+type Constructor = (&'static str, &'static [Type], fn(Vec<Hir>) -> Hir);
+
+/// The conditions which a puzzle can output, in order of their opcode.
+///
+/// Each constructor compiles to a list of the opcode followed by its arguments, so the
+/// opcode and the number and types of the arguments are all checked at compile time.
+const CONDITIONS: [Constructor; 16] = [
+    ("agg_sig_unsafe", &[Type::String, Type::String], |args| {
+        condition(49, args)
+    }),
+    ("agg_sig_me", &[Type::String, Type::String], |args| {
+        condition(50, args)
+    }),
+    ("create_coin", &[Type::String, Type::Int], |args| {
+        condition(51, args)
+    }),
+    ("reserve_fee", &[Type::Int], |args| condition(52, args)),
+    ("create_coin_announcement", &[Type::String], |args| {
+        condition(60, args)
+    }),
+    ("assert_coin_announcement", &[Type::String], |args| {
+        condition(61, args)
+    }),
+    ("create_puzzle_announcement", &[Type::String], |args| {
+        condition(62, args)
+    }),
+    ("assert_puzzle_announcement", &[Type::String], |args| {
+        condition(63, args)
+    }),
+    ("assert_my_coin_id", &[Type::String], |args| {
+        condition(70, args)
+    }),
+    ("assert_my_parent_id", &[Type::String], |args| {
+        condition(71, args)
+    }),
+    ("assert_my_puzzle_hash", &[Type::String], |args| {
+        condition(72, args)
+    }),
+    ("assert_my_amount", &[Type::Int], |args| condition(73, args)),
+    ("assert_seconds_relative", &[Type::Int], |args| {
+        condition(80, args)
+    }),
+    ("assert_seconds_absolute", &[Type::Int], |args| {
+        condition(81, args)
+    }),
+    ("assert_height_relative", &[Type::Int], |args| {
+        condition(82, args)
+    }),
+    ("assert_height_absolute", &[Type::Int], |args| {
+        condition(83, args)
+    }),
+];
+
+/// Defines the `Condition` type and a builtin constructor for each condition.
+pub(crate) fn define_conditions(db: &mut Database, scope: &mut Scope) {
+    scope.define_type("Condition".into(), Type::Condition);
+
+    for (name, param_types, resolver) in CONDITIONS {
+        let symbol_id = db.new_symbol(Symbol::Builtin {
+            param_types: param_types.to_vec(),
+            return_type: Type::Condition,
+            resolver,
+        });
+        scope.define_symbol(name.to_string(), symbol_id);
+    }
+}
+
+fn condition(opcode: u8, args: Vec<Hir>) -> Hir {
+    let mut items = vec![Hir::Int(opcode.into())];
+    items.extend(args);
+    Hir::List(items)
+}
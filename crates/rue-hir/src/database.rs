@@ -2,6 +2,7 @@ use la_arena::Arena;
 
 use crate::{Symbol, SymbolId};
 
+#[derive(Debug, Clone, Default)]
 pub struct Database {
     symbols: Arena<Symbol>,
 }
@@ -0,0 +1,169 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use crate::{BinOp, Database, Hir, Scope, Symbol, SymbolId};
+
+/// Writes each function in the scope as text, in the order they were defined.
+/// This is only meant for debugging the compiler, so it isn't valid source code.
+pub fn dump_hir(db: &Database, scope: &Scope) -> String {
+    let mut output = String::new();
+
+    for symbol_id in scope.defined_symbols() {
+        let Symbol::Function {
+            param_types,
+            return_type,
+            resolved_body,
+            scope: fn_scope,
+            ..
+        } = db.symbol(*symbol_id)
+        else {
+            continue;
+        };
+
+        let scopes = [fn_scope.as_deref(), Some(scope)];
+        let name = |symbol_id: SymbolId| symbol_name(&scopes, symbol_id);
+
+        let params = fn_scope
+            .iter()
+            .flat_map(|fn_scope| fn_scope.defined_symbols())
+            .filter_map(|symbol_id| match db.symbol(*symbol_id) {
+                Symbol::Parameter {
+                    ty,
+                    index,
+                    is_curried,
+                } => {
+                    let curry = if *is_curried { "curry " } else { "" };
+                    Some((*index, format!("{curry}{}: {ty}", name(*symbol_id))))
+                }
+                _ => None,
+            })
+            .sorted()
+            .map(|(_, param)| param)
+            .collect_vec();
+
+        // Unresolved functions don't have a scope, so only the types are known.
+        let params = if fn_scope.is_some() {
+            params.join(", ")
+        } else {
+            param_types.iter().join(", ")
+        };
+
+        write!(output, "fn {}({params}) -> {return_type}", name(*symbol_id)).unwrap();
+
+        let Some(body) = resolved_body else {
+            output.push_str(";\n");
+            continue;
+        };
+
+        output.push_str(" {\n");
+
+        // The variables of the function's block are written on their own lines, and
+        // those of any other block are written inline.
+        let (block_scope, value) = match body {
+            Hir::Block { scope, value } => (Some(scope.as_ref()), value.as_ref()),
+            _ => (None, body),
+        };
+        let name = |symbol_id: SymbolId| {
+            symbol_name(&[block_scope, fn_scope.as_deref(), Some(scope)], symbol_id)
+        };
+
+        for local in block_scope
+            .iter()
+            .flat_map(|block_scope| block_scope.defined_symbols())
+        {
+            writeln!(output, "    {};", let_text(db, &name, *local)).unwrap();
+        }
+
+        writeln!(output, "    {}\n}}", hir_text(db, &name, value)).unwrap();
+    }
+
+    output
+}
+
+fn symbol_name(scopes: &[Option<&Scope>], symbol_id: SymbolId) -> String {
+    scopes
+        .iter()
+        .flatten()
+        .find_map(|scope| scope.symbol_name(symbol_id))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("%{}", symbol_id.into_raw()))
+}
+
+fn let_text(db: &Database, name: &dyn Fn(SymbolId) -> String, symbol_id: SymbolId) -> String {
+    let Symbol::Variable { ty, value } = db.symbol(symbol_id) else {
+        unreachable!("only variables are defined in blocks");
+    };
+    format!(
+        "let {}: {ty} = {}",
+        name(symbol_id),
+        hir_text(db, name, value)
+    )
+}
+
+fn hir_text(db: &Database, name: &dyn Fn(SymbolId) -> String, hir: &Hir) -> String {
+    match hir {
+        Hir::Int(value) => value.to_string(),
+        Hir::String(value) => format!("{value:?}"),
+        Hir::Symbol(symbol_id) => name(*symbol_id),
+        Hir::BinOp { op, lhs, rhs, .. } => {
+            let op = match op {
+                BinOp::Add => "+",
+                BinOp::Sub => "-",
+                BinOp::Mul => "*",
+                BinOp::Div => "/",
+                BinOp::Lt => "<",
+                BinOp::Gt => ">",
+                BinOp::LtBytes => "<s",
+                BinOp::GtBytes => ">s",
+            };
+            format!(
+                "({op} {} {})",
+                hir_text(db, name, lhs),
+                hir_text(db, name, rhs)
+            )
+        }
+        Hir::Call {
+            value, arguments, ..
+        } => {
+            let mut text = format!("({}", hir_text(db, name, value));
+            for argument in arguments {
+                text.push(' ');
+                text.push_str(&hir_text(db, name, argument));
+            }
+            text.push(')');
+            text
+        }
+        Hir::List(items) => format!(
+            "[{}]",
+            items.iter().map(|item| hir_text(db, name, item)).join(", ")
+        ),
+        Hir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => format!(
+            "(if {} {} {})",
+            hir_text(db, name, condition),
+            hir_text(db, name, then_branch),
+            hir_text(db, name, else_branch)
+        ),
+        Hir::Block { scope, value } => {
+            let name = |symbol_id: SymbolId| {
+                scope
+                    .symbol_name(symbol_id)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| name(symbol_id))
+            };
+            let mut text = "{ ".to_string();
+            for local in scope.defined_symbols() {
+                text.push_str(&let_text(db, &name, *local));
+                text.push_str("; ");
+            }
+            text.push_str(&hir_text(db, &name, value));
+            text.push_str(" }");
+            text
+        }
+        Hir::Error => "{error}".to_string(),
+    }
+}
@@ -1,7 +1,20 @@
 use num_bigint::BigInt;
-use rue_ast::BinaryOp;
+use rue_error::TextRange;
 
-use crate::SymbolId;
+use crate::{Scope, SymbolId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Gt,
+    /// Compares byte strings lexicographically, rather than as signed integers.
+    LtBytes,
+    GtBytes,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Hir {
@@ -9,17 +22,31 @@ pub enum Hir {
     String(String),
     Symbol(SymbolId),
     BinOp {
-        op: BinaryOp,
+        op: BinOp,
         lhs: Box<Hir>,
         rhs: Box<Hir>,
+        /// The range of the whole expression, which is kept so that runtime errors
+        /// can be traced back to the source.
+        range: TextRange,
     },
     Call {
         value: Box<Hir>,
         arguments: Vec<Hir>,
+        range: TextRange,
     },
+    List(Vec<Hir>),
     If {
         condition: Box<Hir>,
         then_branch: Box<Hir>,
         else_branch: Box<Hir>,
     },
+    /// A block with local variables, which are only in scope inside of it. Variables
+    /// that are used are evaluated when the block is, before its value.
+    Block {
+        scope: Box<Scope>,
+        value: Box<Hir>,
+    },
+    /// An expression which couldn't be lowered, because of an error that has already
+    /// been reported. Programs with errors are never compiled, so this is never lowered.
+    Error,
 }
@@ -1,43 +1,126 @@
+use std::collections::HashMap;
+
 use itertools::Itertools;
 use rowan::ast::AstNode;
-use rue_ast::{BinaryExpr, Block, CallExpr, Expr, FnItem, IfExpr, Item, LiteralExpr, Program};
-use rue_error::Error;
+use rue_ast::{
+    BinaryExpr, BinaryOp, Block, CallExpr, Expr, FnItem, IfExpr, Item, LetStmt, ListExpr,
+    LiteralExpr, PrefixExpr, PrefixOp, Program, Stmt, TypeRef,
+};
+use rue_error::{Code, Diagnostic, TextRange};
+use rue_lexer::KEYWORDS;
 use rue_syntax::{SyntaxKind, SyntaxToken};
+use suggest::similar_name;
 
+mod conditions;
 mod database;
+mod dump;
 mod hir;
+mod reachability;
 mod scope;
+mod suggest;
 mod symbol;
 mod ty;
 
 pub use database::*;
+pub use dump::*;
 pub use hir::*;
+pub use reachability::*;
 pub use scope::*;
 pub use symbol::*;
 use ty::Type;
 
-pub use rue_ast::BinaryOp;
-
 pub struct Output {
-    pub errors: Vec<Error>,
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
     pub db: Database,
-    pub scope: Option<Scope>,
+    /// The program's scope, which is complete even if there were errors.
+    pub scope: Scope,
 }
 
 pub fn lower(program: Program) -> Output {
+    lower_with_entry_points(program, &["main"])
+}
+
+/// Lowers a program which is compiled from several entry points, rather than just `main`.
+/// Functions that can't be reached from any of them, or from a test, are unused.
+pub fn lower_with_entry_points(program: Program, entry_points: &[&str]) -> Output {
     let mut lowerer = Lowerer::new();
-    let scope = lowerer.lower_program(program);
+    let scope = lowerer.lower_program(program, entry_points);
     Output {
         errors: lowerer.errors,
+        warnings: lowerer.warnings,
         db: lowerer.db,
         scope,
     }
 }
 
+/// The variables defined in a block which are used, along with their values, in the
+/// order that they were defined. Unused variables are never evaluated.
+pub fn local_variables<'a>(
+    db: &'a Database,
+    scope: &'a Scope,
+) -> impl Iterator<Item = (SymbolId, &'a Hir)> + 'a {
+    scope
+        .defined_symbols()
+        .iter()
+        .filter(|symbol_id| scope.used_symbols().contains(*symbol_id))
+        .filter_map(|symbol_id| match db.symbol(*symbol_id) {
+            Symbol::Variable { value, .. } => Some((*symbol_id, value)),
+            _ => None,
+        })
+}
+
+/// The names of the parameters of `main` which are curried into the program,
+/// in the order that their values have to be given.
+pub fn curried_parameters(db: &Database, scope: &Scope) -> Vec<String> {
+    let Some(Symbol::Function {
+        scope: Some(fn_scope),
+        ..
+    }) = scope.lookup_symbol("main").map(|main| db.symbol(main))
+    else {
+        return Vec::new();
+    };
+
+    fn_scope
+        .defined_symbols()
+        .iter()
+        .filter_map(|symbol_id| match db.symbol(*symbol_id) {
+            Symbol::Parameter {
+                index,
+                is_curried: true,
+                ..
+            } => Some((*index, fn_scope.symbol_name(*symbol_id)?.to_string())),
+            _ => None,
+        })
+        .sorted()
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Every function marked with `#[test]`, in the order they were defined.
+pub fn test_functions(db: &Database, scope: &Scope) -> Vec<(String, SymbolId, Test)> {
+    scope
+        .defined_symbols()
+        .iter()
+        .filter_map(|symbol_id| match db.symbol(*symbol_id) {
+            Symbol::Function {
+                test: Some(test), ..
+            } => Some((
+                scope.symbol_name(*symbol_id)?.to_string(),
+                *symbol_id,
+                *test,
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
 struct Lowerer {
     db: Database,
     scopes: Vec<Scope>,
-    errors: Vec<Error>,
+    errors: Vec<Diagnostic>,
+    warnings: Vec<Diagnostic>,
+    declarations: HashMap<SymbolId, SyntaxToken>,
 }
 
 impl Lowerer {
@@ -46,13 +129,16 @@ impl Lowerer {
             db: Database::new(),
             scopes: Vec::new(),
             errors: Vec::new(),
+            warnings: Vec::new(),
+            declarations: HashMap::new(),
         }
     }
 
-    fn lower_program(&mut self, program: Program) -> Option<Scope> {
+    fn lower_program(&mut self, program: Program, entry_points: &[&str]) -> Scope {
         let mut scope = Scope::default();
         scope.define_type("Int".into(), Type::Int);
         scope.define_type("String".into(), Type::String);
+        conditions::define_conditions(&mut self.db, &mut scope);
         self.scopes.push(scope);
 
         let symbol_ids = program
@@ -61,24 +147,63 @@ impl Lowerer {
             .map(|item| self.define_item(item))
             .collect_vec();
 
-        let mut is_valid = true;
-        for (i, item) in program.items().into_iter().enumerate() {
-            if self.lower_item(item, symbol_ids[i]).is_none() {
-                is_valid = false;
+        for (item, symbol_id) in program.items().into_iter().zip(&symbol_ids) {
+            self.lower_item(item, *symbol_id);
+        }
+
+        let scope = self.scopes.pop().unwrap();
+
+        // Functions are type checked even if they are never used, but only those which are
+        // reachable from an entry point or a test will be compiled.
+        let entry_points = entry_points
+            .iter()
+            .filter_map(|name| scope.lookup_symbol(name))
+            .chain(
+                test_functions(&self.db, &scope)
+                    .into_iter()
+                    .map(|test| test.1),
+            )
+            .collect_vec();
+
+        let reachable = reachable_symbols(&self.db, &entry_points);
+
+        // Code which failed to parse or lower can't be followed, so the functions it used
+        // would be reported as unused until it's fixed.
+        let is_incomplete = reachable
+            .iter()
+            .any(|symbol_id| has_errors(&self.db, *symbol_id));
+
+        if !entry_points.is_empty() && !is_incomplete {
+            for symbol_id in symbol_ids {
+                if !reachable.contains(&symbol_id) {
+                    self.warn_unused(symbol_id, "function");
+                }
             }
         }
 
-        is_valid.then(|| self.scopes.pop().unwrap())
+        scope
     }
 
-    fn lower_item(&mut self, item: Item, symbol_id: Option<SymbolId>) -> Option<()> {
+    fn lower_item(&mut self, item: Item, symbol_id: SymbolId) {
         match item {
             Item::Fn(item) => self.lower_fn_item(item, symbol_id),
         }
     }
 
-    fn lower_fn_item(&mut self, item: FnItem, symbol_id: Option<SymbolId>) -> Option<()> {
+    fn lower_fn_item(&mut self, item: FnItem, symbol_id: SymbolId) {
+        let Symbol::Function {
+            param_types,
+            return_type,
+            ..
+        } = self.db.symbol(symbol_id)
+        else {
+            unreachable!("function items are defined as functions");
+        };
+        let (param_types, return_type) = (param_types.clone(), return_type.clone());
+
         let mut fn_scope = Scope::default();
+        let is_main = item.name().is_some_and(|name| name.text() == "main");
+        let mut first_solution_param: Option<TextRange> = None;
 
         for (index, param) in item
             .param_list()
@@ -87,108 +212,223 @@ impl Lowerer {
             .iter()
             .enumerate()
         {
+            // Curried values are at the start of the environment, so they have to come first.
+            let is_curried = if let Some(curry) = param.curry() {
+                if !is_main {
+                    self.errors.push(Diagnostic::error(
+                        Code::CurryOutsideMain,
+                        "only `main` can have curried parameters".to_string(),
+                        curry.text_range().into(),
+                    ));
+                } else if let Some(first) = first_solution_param {
+                    self.errors.push(
+                        Diagnostic::error(
+                            Code::CurryAfterParameter,
+                            "curried parameters must come before the other parameters".to_string(),
+                            curry.text_range().into(),
+                        )
+                        .with_secondary(
+                            first,
+                            "the first parameter read from the solution".to_string(),
+                        ),
+                    );
+                }
+                true
+            } else {
+                first_solution_param.get_or_insert(param.syntax().text_range().into());
+                false
+            };
+
             if let Some(name_token) = param.name() {
-                let name = name_token.text().to_string();
-                let ty = self.lower_type(param.ty()?)?;
-                let symbol_id = self.db.new_symbol(Symbol::Parameter { ty, index });
-                fn_scope.define_symbol(name, symbol_id);
+                let symbol_id = self.db.new_symbol(Symbol::Parameter {
+                    ty: param_types[index].clone(),
+                    index,
+                    is_curried,
+                });
+                fn_scope.define_symbol(name_token.text().to_string(), symbol_id);
+                self.declarations.insert(symbol_id, name_token);
             }
         }
 
+        let first_warning = self.warnings.len();
+
         self.scopes.push(fn_scope);
-        let block = item.block().and_then(|block| self.lower_block(block));
-
-        symbol_id.and_then(|symbol_id| {
-            block.and_then(|(ty, hir)| {
-                let mut error = None;
-
-                if let Symbol::Function {
-                    return_type,
-                    resolved_body,
-                    scope,
-                    ..
-                } = &mut self.db.symbol_mut(symbol_id)
-                {
-                    if !ty.is_assignable_to(return_type) {
-                        error = Some(format!("cannot return value of type `{ty}`, function has return type `{return_type}`"));
-                    }
-                    *resolved_body = Some(hir);
-                    *scope = Some(self.scopes.pop().unwrap());
-                }
+        let (ty, hir) = match item.block() {
+            Some(block) => self.lower_block(block),
+            None => (Type::Unknown, Hir::Error),
+        };
+        let fn_scope = self.scopes.pop().unwrap();
+        self.warn_unused_locals(&fn_scope);
+
+        // Unused names are found as each scope ends, so inner blocks are reported before
+        // the variables and parameters around them unless they're sorted.
+        self.warnings[first_warning..].sort_by_key(Diagnostic::range);
+
+        if !ty.is_assignable_to(&return_type) {
+            self.errors.push(Diagnostic::error(
+                Code::ReturnTypeMismatch,
+                format!(
+                    "cannot return value of type `{ty}`, function has return type `{return_type}`"
+                ),
+                item.syntax().text_range().into(),
+            ));
+        }
+
+        if let Symbol::Function {
+            resolved_body,
+            scope,
+            ..
+        } = self.db.symbol_mut(symbol_id)
+        {
+            *resolved_body = Some(hir);
+            *scope = Some(Box::new(fn_scope));
+        }
+    }
+
+    fn lower_block(&mut self, block: Block) -> (Type, Hir) {
+        self.scopes.push(Scope::default());
+        for stmt in block.stmts() {
+            match stmt {
+                Stmt::Let(stmt) => self.lower_let_stmt(stmt),
+            }
+        }
+        let (ty, value) = self.lower_optional_expr(block.expr());
+        let scope = self.scopes.pop().unwrap();
+
+        self.warn_unused_locals(&scope);
+
+        // Symbols from outer scopes which are used in the block are also used by them.
+        for symbol_id in scope.captured_symbols() {
+            self.scope_mut().mark_used(symbol_id);
+        }
+
+        if scope.defined_symbols().is_empty() {
+            return (ty, value);
+        }
+
+        let hir = Hir::Block {
+            scope: Box::new(scope),
+            value: Box::new(value),
+        };
+        (ty, hir)
+    }
+
+    fn lower_let_stmt(&mut self, stmt: LetStmt) {
+        let (value_type, value) = self.lower_optional_expr(stmt.value());
 
-                if let Some(error) = error {
-                    self.errors.push(Error::new(error, item.syntax().text_range().into()));
-                    None
-                } else {
-                    Some(())
+        // The variable has the type it was annotated with even if the value doesn't match,
+        // so that uses of it are checked against what was intended.
+        let ty = match stmt.ty() {
+            Some(ty) => {
+                let ty = self.lower_type(ty);
+                if !value_type.is_assignable_to(&ty) {
+                    self.errors.push(Diagnostic::error(
+                        Code::AssignTypeMismatch,
+                        format!("cannot assign value of type `{value_type}` to type `{ty}`"),
+                        stmt.syntax().text_range().into(),
+                    ));
                 }
-            })
-        })
+                ty
+            }
+            None => value_type,
+        };
+
+        let Some(name_token) = stmt.name() else {
+            return;
+        };
+
+        let symbol_id = self.db.new_symbol(Symbol::Variable { ty, value });
+        self.scope_mut()
+            .define_symbol(name_token.text().to_string(), symbol_id);
+        self.declarations.insert(symbol_id, name_token);
     }
 
-    fn lower_block(&mut self, block: Block) -> Option<(Type, Hir)> {
-        self.lower_expr(block.expr()?)
+    /// Lowers an expression which may be missing because of a syntax error, which has
+    /// already been reported by the parser.
+    fn lower_optional_expr(&mut self, expr: Option<Expr>) -> (Type, Hir) {
+        match expr {
+            Some(expr) => self.lower_expr(expr),
+            None => (Type::Unknown, Hir::Error),
+        }
     }
 
-    fn lower_expr(&mut self, expr: Expr) -> Option<(Type, Hir)> {
+    fn lower_expr(&mut self, expr: Expr) -> (Type, Hir) {
         match expr {
             Expr::Literal(expr) => self.lower_literal_expr(expr),
             Expr::Binary(expr) => self.lower_binary_expr(expr),
-            Expr::Prefix(_expr) => todo!(),
+            Expr::Prefix(expr) => self.lower_prefix_expr(expr),
             Expr::Call(expr) => self.lower_call_expr(expr),
             Expr::If(expr) => self.lower_if_expr(expr),
+            Expr::List(expr) => self.lower_list_expr(expr),
         }
     }
 
-    fn lower_literal_expr(&mut self, expr: LiteralExpr) -> Option<(Type, Hir)> {
-        let token = expr.token()?;
+    fn lower_literal_expr(&mut self, expr: LiteralExpr) -> (Type, Hir) {
+        let Some(token) = expr.token() else {
+            return (Type::Unknown, Hir::Error);
+        };
         match token.kind() {
             SyntaxKind::Integer => self.lower_integer_expr(token),
             SyntaxKind::String => self.lower_string_expr(token),
             SyntaxKind::Ident => self.lower_ident_expr(token),
-            _ => None,
+            _ => (Type::Unknown, Hir::Error),
         }
     }
 
-    fn lower_integer_expr(&mut self, token: SyntaxToken) -> Option<(Type, Hir)> {
+    fn lower_integer_expr(&mut self, token: SyntaxToken) -> (Type, Hir) {
         let text = token.text();
         match text.parse() {
-            Ok(value) => Some((Type::Int, Hir::Int(value))),
+            Ok(value) => (Type::Int, Hir::Int(value)),
             Err(error) => {
-                self.errors.push(Error::new(
+                self.errors.push(Diagnostic::error(
+                    Code::InvalidInteger,
                     format!("invalid integer literal `{text}` ({error})"),
                     token.text_range().into(),
                 ));
-                None
+                (Type::Int, Hir::Error)
             }
         }
     }
 
-    fn lower_string_expr(&mut self, token: SyntaxToken) -> Option<(Type, Hir)> {
+    fn lower_string_expr(&mut self, token: SyntaxToken) -> (Type, Hir) {
         let text = token.text();
+
+        // Unterminated strings are reported by the parser.
         let mut chars = text.chars();
         if chars.next() != Some('"') || chars.last() != Some('"') {
-            return None;
+            return (Type::String, Hir::Error);
         }
-        Some((Type::String, Hir::String(text.to_string())))
+
+        let value = &text[1..text.len() - 1];
+        (Type::String, Hir::String(value.to_string()))
     }
 
-    fn lower_ident_expr(&mut self, token: SyntaxToken) -> Option<(Type, Hir)> {
+    fn lower_ident_expr(&mut self, token: SyntaxToken) -> (Type, Hir) {
         let name = token.text();
 
         let Some(symbol_id) = self.resolve_name(name) else {
-            self.errors.push(Error::new(
+            let range = token.text_range().into();
+            let mut error = Diagnostic::error(
+                Code::UndefinedVariable,
                 format!("undefined variable `{name}`"),
-                token.text_range().into(),
-            ));
-            return None;
+                range,
+            );
+            if let Some((similar, kind)) = self.similar_symbol(name) {
+                error = error.with_suggestion(
+                    format!("a {kind} with a similar name exists: `{similar}`"),
+                    range,
+                    similar,
+                );
+            }
+            self.errors.push(error);
+            return (Type::Unknown, Hir::Error);
         };
 
         self.scope_mut().mark_used(symbol_id);
 
         let hir = Hir::Symbol(symbol_id);
 
-        Some(match self.db.symbol(symbol_id) {
+        match self.db.symbol(symbol_id) {
             Symbol::Variable { ty, .. } => (ty.clone(), hir),
             Symbol::Parameter { ty, .. } => (ty.clone(), hir),
             Symbol::Function {
@@ -203,67 +443,165 @@ impl Lowerer {
                 hir,
             ),
             Symbol::Builtin { .. } => {
-                self.errors.push(Error::new(
+                self.errors.push(Diagnostic::error(
+                    Code::BuiltinAsValue,
                     format!("builtin function `{name}` cannot be used as a value"),
                     token.text_range().into(),
                 ));
-                return None;
+                (Type::Unknown, Hir::Error)
             }
-        })
+        }
     }
 
-    fn lower_binary_expr(&mut self, expr: BinaryExpr) -> Option<(Type, Hir)> {
-        let (op, token) = expr.op()?;
+    fn lower_binary_expr(&mut self, expr: BinaryExpr) -> (Type, Hir) {
+        let lhs = self.lower_optional_expr(expr.lhs());
+        let rhs = self.lower_optional_expr(expr.rhs());
 
-        let lhs = self.lower_expr(expr.lhs()?)?;
-        let rhs = self.lower_expr(expr.rhs()?)?;
+        let Some((op, token)) = expr.op() else {
+            return (Type::Unknown, Hir::Error);
+        };
+
+        // Every operator returns an `Int`, so that is known even if an operand isn't.
+        if lhs.0 == Type::Unknown || rhs.0 == Type::Unknown {
+            return (Type::Int, Hir::Error);
+        }
+
+        let bin_op = match (op, &lhs.0, &rhs.0) {
+            (BinaryOp::Add, Type::Int, Type::Int) => Some(BinOp::Add),
+            (BinaryOp::Sub, Type::Int, Type::Int) => Some(BinOp::Sub),
+            (BinaryOp::Mul, Type::Int, Type::Int) => Some(BinOp::Mul),
+            (BinaryOp::Div, Type::Int, Type::Int) => Some(BinOp::Div),
+            (BinaryOp::Lt, Type::Int, Type::Int) => Some(BinOp::Lt),
+            (BinaryOp::Gt, Type::Int, Type::Int) => Some(BinOp::Gt),
+            (BinaryOp::Lt, Type::String, Type::String) => Some(BinOp::LtBytes),
+            (BinaryOp::Gt, Type::String, Type::String) => Some(BinOp::GtBytes),
+            _ => None,
+        };
 
-        if lhs.0 != Type::Int || rhs.0 != Type::Int {
-            self.errors.push(Error::new(
+        let Some(bin_op) = bin_op else {
+            self.errors.push(Diagnostic::error(
+                Code::OperatorTypeMismatch,
                 format!(
                     "cannot apply operator `{op}` to values of type `{}` and `{}`",
                     lhs.0, rhs.0
                 ),
                 token.text_range().into(),
             ));
-            return None;
-        }
+            return (Type::Int, Hir::Error);
+        };
 
         let hir = Hir::BinOp {
-            op,
+            op: bin_op,
             lhs: Box::new(lhs.1),
             rhs: Box::new(rhs.1),
+            range: expr.syntax().text_range().into(),
         };
 
-        Some((Type::Int, hir))
+        (Type::Int, hir)
     }
 
-    fn lower_call_expr(&mut self, expr: CallExpr) -> Option<(Type, Hir)> {
-        let target = self.lower_expr(expr.target()?)?;
+    fn lower_prefix_expr(&mut self, expr: PrefixExpr) -> (Type, Hir) {
+        let value = self.lower_optional_expr(expr.expr());
 
-        let args = expr
-            .args()
-            .into_iter()
-            .map(|arg| self.lower_expr(arg))
-            .collect::<Option<Vec<_>>>()?;
+        let Some((op, token)) = expr.op() else {
+            return (Type::Unknown, Hir::Error);
+        };
 
-        let Type::Function {
+        match (op, &value.0) {
+            (_, Type::Unknown) => (Type::Int, Hir::Error),
+            // Negation is subtraction from zero, which CLVM has no separate operator for.
+            (PrefixOp::Neg, Type::Int) => (
+                Type::Int,
+                Hir::BinOp {
+                    op: BinOp::Sub,
+                    lhs: Box::new(Hir::Int(0.into())),
+                    rhs: Box::new(value.1),
+                    range: expr.syntax().text_range().into(),
+                },
+            ),
+            (PrefixOp::Neg, ty) => {
+                self.errors.push(Diagnostic::error(
+                    Code::OperatorTypeMismatch,
+                    format!("cannot apply operator `{op}` to a value of type `{ty}`"),
+                    token.text_range().into(),
+                ));
+                (Type::Int, Hir::Error)
+            }
+        }
+    }
+
+    fn lower_call_expr(&mut self, expr: CallExpr) -> (Type, Hir) {
+        let Some(target) = expr.target() else {
+            return (Type::Unknown, Hir::Error);
+        };
+
+        // Builtins are replaced by the value they resolve to, so they never appear in the HIR.
+        if let Some(Symbol::Builtin {
             param_types,
             return_type,
-        } = target.0
-        else {
-            self.errors.push(Error::new(
-                format!(
-                    "expected callable function, found value of type `{}`",
-                    target.0
-                ),
-                expr.syntax().text_range().into(),
-            ));
-            return None;
+            resolver,
+        }) = self.resolve_builtin(&target).map(|id| self.db.symbol(id))
+        {
+            let (param_types, return_type, resolver) =
+                (param_types.clone(), return_type.clone(), *resolver);
+            let args = self.lower_args(&expr);
+            return match self.check_args(&expr, args, &param_types) {
+                Some(args) => (return_type, resolver(args)),
+                None => (return_type, Hir::Error),
+            };
+        }
+
+        let target = self.lower_expr(target);
+        let args = self.lower_args(&expr);
+
+        let (param_types, return_type) = match target.0 {
+            Type::Function {
+                param_types,
+                return_type,
+            } => (param_types, *return_type),
+            Type::Unknown => return (Type::Unknown, Hir::Error),
+            ty => {
+                self.errors.push(Diagnostic::error(
+                    Code::NotCallable,
+                    format!("expected callable function, found value of type `{ty}`"),
+                    expr.syntax().text_range().into(),
+                ));
+                return (Type::Unknown, Hir::Error);
+            }
+        };
+
+        let Some(arg_hirs) = self.check_args(&expr, args, &param_types) else {
+            return (return_type, Hir::Error);
         };
 
+        (
+            return_type,
+            Hir::Call {
+                value: Box::new(target.1),
+                arguments: arg_hirs,
+                range: expr.syntax().text_range().into(),
+            },
+        )
+    }
+
+    fn lower_args(&mut self, expr: &CallExpr) -> Vec<(Type, Hir)> {
+        expr.args()
+            .into_iter()
+            .map(|arg| self.lower_expr(arg))
+            .collect()
+    }
+
+    /// Checks the arguments against the types of the parameters, reporting each one
+    /// which doesn't match.
+    fn check_args(
+        &mut self,
+        expr: &CallExpr,
+        args: Vec<(Type, Hir)>,
+        param_types: &[Type],
+    ) -> Option<Vec<Hir>> {
         if args.len() != param_types.len() {
-            self.errors.push(Error::new(
+            self.errors.push(Diagnostic::error(
+                Code::ArgumentCount,
                 format!(
                     "expected {} arguments, but was given {}",
                     param_types.len(),
@@ -274,109 +612,297 @@ impl Lowerer {
             return None;
         }
 
-        let mut arg_hirs = Vec::new();
-
-        for (i, arg) in args.iter().enumerate() {
-            let ty = &param_types[i];
+        let mut is_valid = true;
 
-            if !arg.0.is_assignable_to(ty) {
-                self.errors.push(Error::new(
-                    format!("expected argument of type `{}`, but found `{}`", ty, arg.0),
+        for ((arg_type, _), ty) in args.iter().zip(param_types) {
+            if !arg_type.is_assignable_to(ty) {
+                self.errors.push(Diagnostic::error(
+                    Code::ArgumentTypeMismatch,
+                    format!("expected argument of type `{ty}`, but found `{arg_type}`"),
                     expr.syntax().text_range().into(),
                 ));
-                return None;
+                is_valid = false;
             }
+        }
+
+        is_valid.then(|| args.into_iter().map(|(_, hir)| hir).collect())
+    }
+
+    fn resolve_builtin(&self, expr: &Expr) -> Option<SymbolId> {
+        let Expr::Literal(expr) = expr else {
+            return None;
+        };
+        let token = expr
+            .token()
+            .filter(|token| token.kind() == SyntaxKind::Ident)?;
+        let symbol_id = self.resolve_name(token.text())?;
+        matches!(self.db.symbol(symbol_id), Symbol::Builtin { .. }).then_some(symbol_id)
+    }
 
-            arg_hirs.push(arg.1.clone());
+    fn lower_list_expr(&mut self, expr: ListExpr) -> (Type, Hir) {
+        let items = expr
+            .items()
+            .into_iter()
+            .map(|item| self.lower_expr(item))
+            .collect_vec();
+
+        if items.is_empty() {
+            return (Type::Nil, Hir::List(Vec::new()));
         }
 
-        Some((
-            return_type.as_ref().clone(),
-            Hir::Call {
-                value: Box::new(target.1),
-                arguments: arg_hirs,
-            },
-        ))
+        // The first item that has a known type decides the type of the list.
+        let Some(item_type) = items
+            .iter()
+            .map(|(ty, _)| ty)
+            .find(|ty| **ty != Type::Unknown)
+            .cloned()
+        else {
+            return (Type::List(Box::new(Type::Unknown)), Hir::Error);
+        };
+
+        let mut is_valid = true;
+
+        for (ty, _) in &items {
+            if !ty.is_assignable_to(&item_type) {
+                self.errors.push(Diagnostic::error(
+                    Code::ListItemTypeMismatch,
+                    format!("expected list item of type `{item_type}`, but found `{ty}`"),
+                    expr.syntax().text_range().into(),
+                ));
+                is_valid = false;
+            }
+        }
+
+        let hir = if is_valid {
+            Hir::List(items.into_iter().map(|(_, hir)| hir).collect())
+        } else {
+            Hir::Error
+        };
+
+        (Type::List(Box::new(item_type)), hir)
     }
 
-    fn lower_if_expr(&mut self, expr: IfExpr) -> Option<(Type, Hir)> {
-        let condition = self.lower_expr(expr.condition()?)?;
-        let then_block = self.lower_block(expr.then_block()?)?;
-        let else_block = self.lower_block(expr.else_block()?)?;
+    fn lower_if_expr(&mut self, expr: IfExpr) -> (Type, Hir) {
+        let condition = self.lower_optional_expr(expr.condition());
+        let then_block = match expr.then_block() {
+            Some(block) => self.lower_block(block),
+            None => (Type::Unknown, Hir::Error),
+        };
+        let else_block = match expr.else_block() {
+            Some(block) => self.lower_block(block),
+            None => (Type::Unknown, Hir::Error),
+        };
 
-        if then_block.0 != else_block.0 {
-            self.errors.push(Error::new(
+        // An empty list can be returned from one branch when the other returns a list.
+        let ty = if then_block.0 == Type::Unknown {
+            else_block.0
+        } else if else_block.0.is_assignable_to(&then_block.0) {
+            then_block.0
+        } else if then_block.0.is_assignable_to(&else_block.0) {
+            else_block.0
+        } else {
+            self.errors.push(Diagnostic::error(
+                Code::BranchTypeMismatch,
                 format!(
                     "then branch has type `{}`, but else branch has differing type `{}`",
                     then_block.0, else_block.0
                 ),
                 expr.syntax().text_range().into(),
             ));
-            return None;
-        }
+            return (Type::Unknown, Hir::Error);
+        };
 
-        Some((
-            then_block.0,
+        (
+            ty,
             Hir::If {
                 condition: Box::new(condition.1),
                 then_branch: Box::new(then_block.1),
                 else_branch: Box::new(else_block.1),
             },
-        ))
+        )
     }
 
-    fn lower_type(&mut self, token: SyntaxToken) -> Option<Type> {
-        match self.resolve_type(token.text()) {
-            Some(ty) => Some(ty.clone()),
-            None => {
-                self.errors.push(Error::new(
-                    format!("undefined type `{token}`"),
-                    token.text_range().into(),
-                ));
-                None
+    /// Lowers a type which may be missing because of a syntax error, which has already
+    /// been reported by the parser.
+    fn lower_optional_type(&mut self, ty: Option<TypeRef>) -> Type {
+        match ty {
+            Some(ty) => self.lower_type(ty),
+            None => Type::Unknown,
+        }
+    }
+
+    fn lower_type(&mut self, ty: TypeRef) -> Type {
+        let token = match ty {
+            TypeRef::Named(token) => token,
+            TypeRef::List(list_type) => {
+                let item_type = self.lower_optional_type(list_type.item_type());
+                return Type::List(Box::new(item_type));
             }
+        };
+
+        if let Some(ty) = self.resolve_type(token.text()) {
+            return ty.clone();
         }
+
+        let range = token.text_range().into();
+        let mut error = Diagnostic::error(
+            Code::UndefinedType,
+            format!("undefined type `{token}`"),
+            range,
+        );
+        if let Some(similar) = self.similar_type(token.text()) {
+            error = error.with_suggestion(
+                format!("a type with a similar name exists: `{similar}`"),
+                range,
+                similar,
+            );
+        }
+        self.errors.push(error);
+        Type::Unknown
     }
 
-    fn define_item(&mut self, item: Item) -> Option<SymbolId> {
+    fn define_item(&mut self, item: Item) -> SymbolId {
         match item {
             Item::Fn(item) => self.define_fn_item(item),
         }
     }
 
-    fn define_fn_item(&mut self, item: FnItem) -> Option<SymbolId> {
-        let name_token = item.name()?;
-        let name = name_token.text().to_string();
-
-        if self.scope().lookup_symbol(&name).is_some() {
-            self.errors.push(Error::new(
-                format!("there is already a variable named `{name}`"),
-                name_token.text_range().into(),
-            ));
-            return None;
-        }
-
-        let mut param_types = Vec::new();
-        for param_type in item
+    /// Defines the function's symbol. Functions without a name, or with the name of
+    /// something else, still get a symbol so that their bodies are checked, but it
+    /// can't be referred to.
+    fn define_fn_item(&mut self, item: FnItem) -> SymbolId {
+        let param_types = item
             .param_list()
             .map(|list| list.params())
             .unwrap_or_default()
-        {
-            param_types.push(self.lower_type(param_type.ty()?)?);
-        }
+            .into_iter()
+            .map(|param| self.lower_optional_type(param.ty()))
+            .collect();
 
-        let return_type = self.lower_type(item.return_type()?)?;
+        let return_type = self.lower_optional_type(item.return_type());
+        let test = self.lower_attributes(&item);
 
         let symbol = self.db.new_symbol(Symbol::Function {
             param_types,
             return_type,
             resolved_body: None,
             scope: None,
+            is_inline: item.inline().is_some(),
+            test,
         });
 
+        let Some(name_token) = item.name() else {
+            return symbol;
+        };
+        let name = name_token.text().to_string();
+
+        if let Some(existing) = self.scope().lookup_symbol(&name) {
+            let error = Diagnostic::error(
+                Code::DuplicateDefinition,
+                format!("there is already a variable named `{name}`"),
+                name_token.text_range().into(),
+            )
+            .with_label("redefined here".to_string());
+
+            let error = match self.declarations.get(&existing) {
+                Some(token) => error.with_secondary(
+                    token.text_range().into(),
+                    format!("`{name}` is first defined here"),
+                ),
+                None => error.with_note(format!("`{name}` is a builtin function")),
+            };
+
+            self.errors.push(error);
+            return symbol;
+        }
+
         self.scope_mut().define_symbol(name, symbol);
+        self.declarations.insert(symbol, name_token);
+
+        symbol
+    }
+
+    fn lower_attributes(&mut self, item: &FnItem) -> Option<Test> {
+        let mut test = None;
+        let mut should_fail = None;
+
+        for attribute in item.attributes() {
+            let Some(name) = attribute.name() else {
+                continue;
+            };
+            match name.text() {
+                "test" => test = Some(name),
+                "should_fail" => should_fail = Some(name),
+                _ => self.errors.push(Diagnostic::error(
+                    Code::UnknownAttribute,
+                    format!("unknown attribute `{}`", name.text()),
+                    name.text_range().into(),
+                )),
+            }
+        }
+
+        let Some(test) = test else {
+            if let Some(should_fail) = should_fail {
+                self.errors.push(Diagnostic::error(
+                    Code::ShouldFailWithoutTest,
+                    "`#[should_fail]` can only be used on tests".to_string(),
+                    should_fail.text_range().into(),
+                ));
+            }
+            return None;
+        };
+
+        // Tests are run without a solution, so there is nothing to pass to parameters.
+        if item
+            .param_list()
+            .is_some_and(|list| !list.params().is_empty())
+        {
+            self.errors.push(Diagnostic::error(
+                Code::TestParameters,
+                "test functions can't have parameters".to_string(),
+                test.text_range().into(),
+            ));
+        }
+
+        Some(Test {
+            should_fail: should_fail.is_some(),
+        })
+    }
 
-        Some(symbol)
+    fn warn_unused_locals(&mut self, scope: &Scope) {
+        for defined_symbol in scope.defined_symbols() {
+            if scope.used_symbols().contains(defined_symbol) {
+                continue;
+            }
+            match self.db.symbol(*defined_symbol) {
+                Symbol::Parameter { .. } => self.warn_unused(*defined_symbol, "parameter"),
+                Symbol::Variable { .. } => self.warn_unused(*defined_symbol, "variable"),
+                _ => {}
+            }
+        }
+    }
+
+    fn warn_unused(&mut self, symbol_id: SymbolId, kind: &str) {
+        let Some(token) = self.declarations.get(&symbol_id) else {
+            return;
+        };
+
+        // Names starting with an underscore are intentionally unused.
+        let name = token.text();
+        if name.starts_with('_') || name == "main" {
+            return;
+        }
+
+        self.warnings.push(
+            Diagnostic::warning(
+                Code::Unused,
+                format!("unused {kind} `{name}`"),
+                token.text_range().into(),
+            )
+            .with_help(format!(
+                "if this is intentional, prefix it with an underscore: `_{name}`"
+            )),
+        );
     }
 
     fn resolve_name(&self, name: &str) -> Option<SymbolId> {
@@ -393,6 +919,39 @@ impl Lowerer {
             .find_map(|scope| scope.lookup_type(name))
     }
 
+    /// Finds a symbol in scope or a keyword which an undefined name is likely a typo of,
+    /// along with what kind of name it is.
+    fn similar_symbol(&self, name: &str) -> Option<(String, &'static str)> {
+        let symbols =
+            self.scopes
+                .iter()
+                .rev()
+                .flat_map(Scope::symbol_names)
+                .map(|(name, symbol_id)| {
+                    let kind = match self.db.symbol(symbol_id) {
+                        Symbol::Variable { .. } => "variable",
+                        Symbol::Parameter { .. } => "parameter",
+                        Symbol::Function { .. } => "function",
+                        Symbol::Builtin { .. } => "builtin function",
+                    };
+                    (name, kind)
+                });
+        let keywords = KEYWORDS.into_iter().map(|keyword| (keyword, "keyword"));
+
+        similar_name(name, symbols.chain(keywords))
+            .map(|(similar, kind)| (similar.to_string(), kind))
+    }
+
+    fn similar_type(&self, name: &str) -> Option<String> {
+        let types = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(Scope::type_names)
+            .map(|name| (name, ()));
+        similar_name(name, types).map(|(similar, _)| similar.to_string())
+    }
+
     fn scope(&self) -> &Scope {
         self.scopes.last().unwrap()
     }
@@ -401,3 +960,398 @@ impl Lowerer {
         self.scopes.last_mut().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rue_parser::parse_text;
+
+    use super::*;
+
+    fn warnings(source: &str) -> Vec<String> {
+        let (errors, node) = parse_text(source);
+        assert_eq!(errors, Vec::new());
+
+        let output = lower(Program::cast(node).unwrap());
+        assert_eq!(output.errors, Vec::new());
+
+        output
+            .warnings
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn unused_functions() {
+        assert_eq!(
+            warnings(
+                "
+                fn main() -> Int { used() }
+                fn used() -> Int { 42 }
+                fn dead() -> Int { also_dead() }
+                fn also_dead() -> Int { dead() }
+                "
+            ),
+            vec![
+                "unused function `dead` at 104-108",
+                "unused function `also_dead` at 153-162"
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_after_parse_errors() {
+        let source = r#"
+            fn helper(x: Int) -> Int { x + 1 }
+            fn main(value: Int) -> Int { let y = helper(value); y + }
+        "#;
+
+        let (errors, node) = parse_text(source);
+        assert!(!errors.is_empty());
+
+        let output = lower(Program::cast(node).unwrap());
+        assert_eq!(output.warnings, Vec::new());
+    }
+
+    #[test]
+    fn unused_with_entry_points() {
+        let source = "
+            fn helper() -> Int { 42 }
+            fn first() -> Int { helper() }
+            fn second() -> Int { helper() + 1 }
+            fn dead() -> Int { 0 }
+        ";
+        let (_, node) = parse_text(source);
+        let output = lower_with_entry_points(Program::cast(node).unwrap(), &["first", "second"]);
+        assert_eq!(
+            output
+                .warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect_vec(),
+            vec!["unused function `dead` at 145-149"]
+        );
+    }
+
+    #[test]
+    fn unused_parameters_and_variables() {
+        assert_eq!(
+            warnings(
+                "
+                fn main(a: Int, b: Int, _c: Int) -> Int {
+                    let x = a;
+                    let y = x * 2;
+                    let _z = 3;
+                    x
+                }
+                "
+            ),
+            vec![
+                "unused parameter `b` at 33-34",
+                "unused variable `y` at 114-115"
+            ]
+        );
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        let (_, node) = parse_text(source);
+        lower(Program::cast(node).unwrap())
+            .errors
+            .iter()
+            .map(Diagnostic::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn condition_constructors() {
+        assert_eq!(
+            errors(
+                "fn main(p: String) -> [Condition] {
+                    if 1 { [create_coin(p, 1), reserve_fee(5)] } else { [] }
+                }"
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            errors("fn main() -> Condition { reserve_fee(1, 2) }"),
+            vec!["expected 1 arguments, but was given 2 at 25-43"]
+        );
+        assert_eq!(
+            errors("fn main() -> Condition { create_coin(1, 1) }"),
+            vec!["expected argument of type `String`, but found `Int` at 25-43"]
+        );
+        assert_eq!(
+            errors("fn main() -> [Condition] { [reserve_fee(1), 2] }"),
+            vec!["expected list item of type `Condition`, but found `Int` at 27-47"]
+        );
+        assert_eq!(
+            errors("fn main() -> Int { reserve_fee }"),
+            vec!["builtin function `reserve_fee` cannot be used as a value at 19-30"]
+        );
+    }
+
+    #[test]
+    fn block_scopes() {
+        assert_eq!(
+            errors(
+                "fn main(x: Int) -> Int {
+                    let z = if x > 0 { let q = 5; q } else { 0 };
+                    q + z
+                }"
+            ),
+            vec!["undefined variable `q` at 111-112"]
+        );
+
+        assert_eq!(
+            warnings(
+                "fn main(x: Int) -> Int {
+                    let y = 1;
+                    if x > 0 { let a = 2; x } else { 0 }
+                }"
+            ),
+            vec![
+                "unused variable `y` at 49-50",
+                "unused variable `a` at 91-92"
+            ]
+        );
+    }
+
+    #[test]
+    fn curried_parameters_of_main() {
+        let (_, node) = parse_text(
+            "fn main(curry b: Int, curry a: String, x: Int) -> Int { b + x + helper(x) }
+            fn helper(n: Int) -> Int { n }",
+        );
+        let output = lower(Program::cast(node).unwrap());
+        assert_eq!(output.errors, Vec::new());
+        assert_eq!(
+            curried_parameters(&output.db, &output.scope),
+            vec!["b", "a"]
+        );
+
+        assert_eq!(
+            errors("fn main(x: Int, curry y: Int) -> Int { x + y }"),
+            vec!["curried parameters must come before the other parameters at 16-21"]
+        );
+        assert_eq!(
+            errors("fn main() -> Int { f(1) } fn f(curry y: Int) -> Int { y }"),
+            vec!["only `main` can have curried parameters at 31-36"]
+        );
+    }
+
+    #[test]
+    fn duplicate_definitions() {
+        let (_, node) = parse_text("fn f() -> Int { 1 } fn f() -> Int { 2 }");
+        let output = lower(Program::cast(node).unwrap());
+
+        let [error] = output.errors.as_slice() else {
+            panic!("expected one error, found {:?}", output.errors);
+        };
+        assert_eq!(error.code(), Code::DuplicateDefinition);
+        assert_eq!(error.range(), (23..24).into());
+        assert_eq!(
+            error
+                .secondary()
+                .iter()
+                .map(|label| label.range)
+                .collect_vec(),
+            vec![(3..4).into()]
+        );
+    }
+
+    #[test]
+    fn similar_name_suggestions() {
+        let suggestions = |source: &str| {
+            let (_, node) = parse_text(source);
+            lower(Program::cast(node).unwrap())
+                .errors
+                .iter()
+                .flat_map(|error| error.notes().to_vec())
+                .map(|note| {
+                    let suggestion = note.suggestion.unwrap();
+                    (note.message, suggestion.range, suggestion.replacement)
+                })
+                .collect_vec()
+        };
+
+        assert_eq!(
+            suggestions("fn main(amount: Int) -> Int { amont }"),
+            vec![(
+                "a parameter with a similar name exists: `amount`".to_string(),
+                (30..35).into(),
+                "amount".to_string()
+            )]
+        );
+        assert_eq!(
+            suggestions("fn main() -> Int { let total = 1; totl }"),
+            vec![(
+                "a variable with a similar name exists: `total`".to_string(),
+                (34..38).into(),
+                "total".to_string()
+            )]
+        );
+        assert_eq!(
+            suggestions("fn main() -> int { helpr() } fn helper() -> Int { reserve_fe }"),
+            vec![
+                (
+                    "a type with a similar name exists: `Int`".to_string(),
+                    (13..16).into(),
+                    "Int".to_string()
+                ),
+                (
+                    "a function with a similar name exists: `helper`".to_string(),
+                    (19..24).into(),
+                    "helper".to_string()
+                ),
+                (
+                    "a builtin function with a similar name exists: `reserve_fee`".to_string(),
+                    (50..60).into(),
+                    "reserve_fee".to_string()
+                )
+            ]
+        );
+        assert_eq!(
+            suggestions("fn main() -> Int { curr }")[0].0,
+            "a keyword with a similar name exists: `curry`"
+        );
+        assert_eq!(suggestions("fn main() -> Int { nothing_like_it }"), vec![]);
+    }
+
+    #[test]
+    fn test_attributes() {
+        let source = "
+            fn main() -> Int { 1 }
+            fn helper() -> Int { 2 }
+            #[test] fn passes() -> Int { helper() }
+            #[test] #[should_fail] fn fails() -> Int { 1 / 0 }
+        ";
+        let (_, node) = parse_text(source);
+        let output = lower(Program::cast(node).unwrap());
+        assert_eq!(output.errors, Vec::new());
+        assert_eq!(output.warnings, Vec::new());
+
+        let tests = test_functions(&output.db, &output.scope)
+            .into_iter()
+            .map(|(name, _, test)| (name, test.should_fail))
+            .collect_vec();
+        assert_eq!(
+            tests,
+            vec![("passes".to_string(), false), ("fails".to_string(), true)]
+        );
+
+        assert_eq!(
+            errors("#[tset] fn f() -> Int { 1 }"),
+            vec!["unknown attribute `tset` at 2-6"]
+        );
+        assert_eq!(
+            errors("#[should_fail] fn f() -> Int { 1 }"),
+            vec!["`#[should_fail]` can only be used on tests at 2-13"]
+        );
+        assert_eq!(
+            errors("#[test] fn f(x: Int) -> Int { x }"),
+            vec!["test functions can't have parameters at 2-6"]
+        );
+    }
+
+    #[test]
+    fn explanation_examples() {
+        for code in Code::ALL {
+            // Code blocks alternate with the text between them, and start with their tags.
+            for block in code.explanation().split("```").skip(1).step_by(2) {
+                let (tags, source) = block.split_once('\n').unwrap();
+
+                let (parse_errors, node) = parse_text(source);
+                let output = lower(Program::cast(node).unwrap());
+                let codes = parse_errors
+                    .iter()
+                    .chain(&output.errors)
+                    .chain(&output.warnings)
+                    .map(Diagnostic::code)
+                    .collect_vec();
+
+                match tags {
+                    "rue" => assert_eq!(codes, Vec::new(), "corrected example of {code}"),
+                    "rue,compile_fail" => assert!(
+                        codes.contains(code),
+                        "erroneous example of {code} produced {codes:?}"
+                    ),
+                    "rue,ignore" => {}
+                    _ => panic!("unknown tags `{tags}` in the explanation of {code}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn independent_errors() {
+        let source = r#"
+            fn main(x: Strin) -> Int {
+                let a = undefined;
+                let b: Int = "text";
+                a + b + x + helper(1, 2)
+            }
+            fn helper(value: Int) -> Int { value + "one" }
+            fn helper() -> Int { -"negative" }
+        "#;
+        let (parse_errors, node) = parse_text(source);
+        assert_eq!(parse_errors, Vec::new());
+
+        let output = lower(Program::cast(node).unwrap());
+        assert_eq!(
+            output
+                .errors
+                .iter()
+                .map(Diagnostic::to_string)
+                .collect_vec(),
+            vec![
+                "undefined type `Strin` at 24-29",
+                "there is already a variable named `helper` at 241-247",
+                "undefined variable `undefined` at 64-73",
+                "cannot assign value of type `String` to type `Int` at 91-128",
+                "expected 1 arguments, but was given 2 at 139-165",
+                "cannot apply operator `+` to values of type `Int` and `String` at 216-217",
+                "cannot apply operator `-` to a value of type `String` at 259-260",
+            ]
+        );
+
+        // Everything that could be lowered is kept, so that tools can still use it.
+        assert_eq!(
+            dump_hir(&output.db, &output.scope),
+            r#"fn main(x: {unknown}) -> Int {
+    let a: {unknown} = {error};
+    let b: Int = "text";
+    (+ {error} {error})
+}
+fn helper(value: Int) -> Int {
+    {error}
+}
+"#
+        );
+    }
+
+    #[test]
+    fn dump() {
+        let (_, node) = parse_text(
+            r#"
+            fn main(name: String, n: Int) -> Int {
+                let double = n * 2;
+                if name < "m" { helper(double) } else { 0 }
+            }
+            fn helper(value: Int) -> Int { value + 1 }
+            "#,
+        );
+        let output = lower(Program::cast(node).unwrap());
+
+        assert_eq!(
+            dump_hir(&output.db, &output.scope),
+            r#"fn main(name: String, n: Int) -> Int {
+    let double: Int = (* n 2);
+    (if (<s name "m") (helper double) 0)
+}
+fn helper(value: Int) -> Int {
+    (+ value 1)
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use indexmap::IndexSet;
+
+use crate::{Database, Hir, Symbol, SymbolId};
+
+/// Computes every symbol that can be reached from the given entry points.
+///
+/// This follows function bodies and the values of their local variables, so a function
+/// which is only referenced by unreachable code is itself unreachable.
+pub fn reachable_symbols(db: &Database, entry_points: &[SymbolId]) -> IndexSet<SymbolId> {
+    let mut reachable = IndexSet::new();
+    let mut stack = entry_points.to_vec();
+
+    while let Some(symbol_id) = stack.pop() {
+        if !reachable.insert(symbol_id) {
+            continue;
+        }
+
+        match db.symbol(symbol_id) {
+            Symbol::Function {
+                resolved_body: Some(body),
+                ..
+            } => collect_symbols(body, &mut stack),
+            Symbol::Variable { value, .. } => collect_symbols(value, &mut stack),
+            _ => {}
+        }
+    }
+
+    reachable
+}
+
+/// Whether the body of a function or the value of a variable failed to lower, in which
+/// case the symbols it referred to may be missing from it.
+pub(crate) fn has_errors(db: &Database, symbol_id: SymbolId) -> bool {
+    match db.symbol(symbol_id) {
+        Symbol::Function {
+            resolved_body: Some(body),
+            ..
+        } => contains_error(body),
+        Symbol::Variable { value, .. } => contains_error(value),
+        _ => false,
+    }
+}
+
+fn contains_error(hir: &Hir) -> bool {
+    match hir {
+        Hir::Error => true,
+        Hir::Int(_) | Hir::String(_) | Hir::Symbol(_) => false,
+        Hir::BinOp { lhs, rhs, .. } => contains_error(lhs) || contains_error(rhs),
+        Hir::Call {
+            value, arguments, ..
+        } => contains_error(value) || arguments.iter().any(contains_error),
+        Hir::List(items) => items.iter().any(contains_error),
+        Hir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            contains_error(condition) || contains_error(then_branch) || contains_error(else_branch)
+        }
+        Hir::Block { value, .. } => contains_error(value),
+    }
+}
+
+fn collect_symbols(hir: &Hir, symbols: &mut Vec<SymbolId>) {
+    match hir {
+        Hir::Int(_) | Hir::String(_) | Hir::Error => {}
+        Hir::Symbol(symbol_id) => symbols.push(*symbol_id),
+        Hir::BinOp { lhs, rhs, .. } => {
+            collect_symbols(lhs, symbols);
+            collect_symbols(rhs, symbols);
+        }
+        Hir::Call {
+            value, arguments, ..
+        } => {
+            collect_symbols(value, symbols);
+            for argument in arguments {
+                collect_symbols(argument, symbols);
+            }
+        }
+        Hir::List(items) => {
+            for item in items {
+                collect_symbols(item, symbols);
+            }
+        }
+        Hir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            collect_symbols(condition, symbols);
+            collect_symbols(then_branch, symbols);
+            collect_symbols(else_branch, symbols);
+        }
+        // Variables are followed when they're referenced, like any other symbol.
+        Hir::Block { value, .. } => collect_symbols(value, symbols),
+    }
+}
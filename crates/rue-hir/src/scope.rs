@@ -1,10 +1,11 @@
 use std::collections::HashMap;
 
 use indexmap::IndexSet;
+use itertools::Itertools;
 
 use crate::{ty::Type, SymbolId};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Scope {
     named_symbols: HashMap<String, SymbolId>,
     named_types: HashMap<String, Type>,
@@ -17,6 +18,22 @@ impl Scope {
         self.named_symbols.get(name).copied()
     }
 
+    /// Finds the name that currently refers to a symbol, if it hasn't been shadowed.
+    pub fn symbol_name(&self, symbol_id: SymbolId) -> Option<&str> {
+        self.named_symbols
+            .iter()
+            .find(|(_, id)| **id == symbol_id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Every name defined in this scope, in alphabetical order.
+    pub fn symbol_names(&self) -> impl Iterator<Item = (&str, SymbolId)> {
+        self.named_symbols
+            .iter()
+            .map(|(name, id)| (name.as_str(), *id))
+            .sorted()
+    }
+
     pub fn define_symbol(&mut self, name: String, symbol_id: SymbolId) {
         self.named_symbols.insert(name, symbol_id);
         self.defined_symbols.insert(symbol_id);
@@ -38,6 +55,11 @@ impl Scope {
         self.named_types.get(name)
     }
 
+    /// Every type name defined in this scope, in alphabetical order.
+    pub fn type_names(&self) -> impl Iterator<Item = &str> {
+        self.named_types.keys().map(String::as_str).sorted()
+    }
+
     pub fn define_type(&mut self, name: String, ty: Type) {
         self.named_types.insert(name, ty);
     }
@@ -0,0 +1,78 @@
+/// The number of characters which have to be inserted, removed or replaced to turn
+/// one string into the other.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+
+    // Only the previous row of the table is needed to calculate the next one.
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Finds the candidate which the name is most likely a typo of, if any are close enough.
+///
+/// Candidates which only differ in case are preferred, then the closest by edit
+/// distance. Ties go to whichever candidate comes first.
+pub(crate) fn similar_name<'a, T>(
+    name: &str,
+    candidates: impl IntoIterator<Item = (&'a str, T)>,
+) -> Option<(&'a str, T)> {
+    let max_distance = name.chars().count().max(3) / 3;
+
+    candidates
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, (candidate, value))| {
+            if candidate == name {
+                return None;
+            }
+            let distance = if candidate.eq_ignore_ascii_case(name) {
+                0
+            } else {
+                edit_distance(name, candidate)
+            };
+            (distance <= max_distance).then_some((distance, index, candidate, value))
+        })
+        .min_by_key(|(distance, index, ..)| (*distance, *index))
+        .map(|(_, _, candidate, value)| (candidate, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("answer", "answer"), 0);
+        assert_eq!(edit_distance("anwser", "answer"), 2);
+        assert_eq!(edit_distance("helper", "helpr"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn similar_names() {
+        let candidates = [("amount", 1), ("answer", 2), ("Int", 3), ("x", 4)];
+        assert_eq!(similar_name("anser", candidates), Some(("answer", 2)));
+        assert_eq!(similar_name("int", candidates), Some(("Int", 3)));
+        assert_eq!(similar_name("y", candidates), Some(("x", 4)));
+        assert_eq!(similar_name("total", candidates), None);
+        assert_eq!(similar_name("amount", candidates), None);
+
+        // Ties go to the first candidate, which is the closest scope.
+        assert_eq!(similar_name("ab", [("ac", 1), ("ad", 2)]), Some(("ac", 1)));
+    }
+}
@@ -4,7 +4,7 @@ use crate::{ty::Type, Hir, Scope};
 
 pub type SymbolId = Idx<Symbol>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Symbol {
     Variable {
         ty: Type,
@@ -13,12 +13,17 @@ pub enum Symbol {
     Parameter {
         ty: Type,
         index: usize,
+        /// Whether the value is curried into the program, rather than read from the solution.
+        is_curried: bool,
     },
     Function {
         param_types: Vec<Type>,
         return_type: Type,
         resolved_body: Option<Hir>,
-        scope: Option<Scope>,
+        scope: Option<Box<Scope>>,
+        is_inline: bool,
+        /// Set if the function is marked with `#[test]`.
+        test: Option<Test>,
     },
     Builtin {
         param_types: Vec<Type>,
@@ -26,3 +31,10 @@ pub enum Symbol {
         resolver: fn(args: Vec<Hir>) -> Hir,
     },
 }
+
+/// A function marked with `#[test]`, which is compiled and run as its own entry point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Test {
+    /// Whether the test is expected to raise an error, set by `#[should_fail]`.
+    pub should_fail: bool,
+}
@@ -6,17 +6,35 @@ use itertools::Itertools;
 pub enum Type {
     Int,
     String,
+    /// A condition which is output by a puzzle, such as `CREATE_COIN`.
+    Condition,
+    List(Box<Type>),
+    /// The type of an empty list, which can be assigned to any list type.
+    Nil,
     Function {
         param_types: Vec<Type>,
         return_type: Box<Type>,
     },
+    /// The type of an expression which had an error. It can be assigned to and from any
+    /// type, so that one error doesn't cause others.
+    Unknown,
 }
 
 impl Type {
     pub fn is_assignable_to(&self, target: &Type) -> bool {
+        if *target == Type::Unknown {
+            return true;
+        }
+
         match self {
             Type::Int => matches!(target, Type::Int),
             Type::String => matches!(target, Type::String),
+            Type::Condition => matches!(target, Type::Condition),
+            Type::List(item) => match target {
+                Type::List(target_item) => item.is_assignable_to(target_item),
+                _ => false,
+            },
+            Type::Nil => matches!(target, Type::List(_) | Type::Nil),
             Type::Function {
                 param_types,
                 return_type,
@@ -35,6 +53,7 @@ impl Type {
                     false
                 }
             }
+            Type::Unknown => true,
         }
     }
 }
@@ -44,12 +63,16 @@ impl fmt::Display for Type {
         match self {
             Self::Int => write!(f, "Int"),
             Self::String => write!(f, "String"),
+            Self::Condition => write!(f, "Condition"),
+            Self::List(item) => write!(f, "[{item}]"),
+            Self::Nil => write!(f, "[]"),
             Self::Function {
                 param_types,
                 return_type,
             } => {
                 write!(f, "fn({}) -> {return_type}", param_types.iter().join(", "))
             }
+            Self::Unknown => write!(f, "{{unknown}}"),
         }
     }
 }
@@ -8,6 +8,9 @@ pub use token_kind::*;
 
 const EOF: char = '\0';
 
+/// Every word which is lexed as a keyword rather than an identifier.
+pub const KEYWORDS: [&str; 6] = ["fn", "inline", "curry", "if", "else", "let"];
+
 impl<'a> Lexer<'a> {
     fn token(&mut self) -> Option<Token<'a>> {
         let start = self.pos();
@@ -34,6 +37,7 @@ impl<'a> Lexer<'a> {
             '<' => TokenKind::LessThan,
             '=' => TokenKind::Equals,
 
+            '#' => TokenKind::Pound,
             '.' => TokenKind::Dot,
             ',' => TokenKind::Comma,
             ':' => TokenKind::Colon,
@@ -101,6 +105,8 @@ impl<'a> Lexer<'a> {
 
         match ident.as_str() {
             "fn" => TokenKind::Fn,
+            "inline" => TokenKind::Inline,
+            "curry" => TokenKind::Curry,
             "if" => TokenKind::If,
             "else" => TokenKind::Else,
             "let" => TokenKind::Let,
@@ -129,7 +135,7 @@ fn is_whitespace(c: char) -> bool {
 }
 
 fn is_digit(c: char) -> bool {
-    matches!(c, '0'..='9')
+    c.is_ascii_digit()
 }
 
 #[cfg(test)]
@@ -194,9 +200,16 @@ mod tests {
         check("A", &[TokenKind::Ident]);
         check("_0", &[TokenKind::Ident]);
         check("fn", &[TokenKind::Fn]);
+        check("inline", &[TokenKind::Inline]);
+        check("curry", &[TokenKind::Curry]);
         check("if", &[TokenKind::If]);
         check("else", &[TokenKind::Else]);
         check("let", &[TokenKind::Let]);
+
+        for keyword in KEYWORDS {
+            let kinds: Vec<TokenKind> = Lexer::new(keyword).map(|token| token.kind).collect();
+            assert_ne!(kinds, [TokenKind::Ident], "`{keyword}` is not a keyword");
+        }
     }
 
     #[test]
@@ -252,6 +265,7 @@ mod tests {
         check(">", &[TokenKind::GreaterThan]);
         check("<", &[TokenKind::LessThan]);
         check("=", &[TokenKind::Equals]);
+        check("#", &[TokenKind::Pound]);
         check(".", &[TokenKind::Dot]);
         check(",", &[TokenKind::Comma]);
         check(":", &[TokenKind::Colon]);
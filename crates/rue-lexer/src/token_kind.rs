@@ -10,6 +10,8 @@ pub enum TokenKind {
     String { is_terminated: bool },
 
     Fn,
+    Inline,
+    Curry,
     If,
     Else,
     Let,
@@ -30,6 +32,7 @@ pub enum TokenKind {
     LessThan,
     Equals,
 
+    Pound,
     Dot,
     Comma,
     Colon,
@@ -0,0 +1,113 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexSet;
+use rue_hir::{local_variables, Database, Hir, Symbol, SymbolId};
+
+/// Computes the symbols each function needs to be passed in its environment.
+///
+/// A function captures every symbol it references that it doesn't define itself, as well
+/// as the captures of any function it calls, since those have to be forwarded at the call site.
+/// Calls to inlined functions are replaced by their bodies, so only their captures are needed.
+pub(crate) fn compute_captures(
+    db: &Database,
+    functions: &[SymbolId],
+    inlined: &HashSet<SymbolId>,
+) -> HashMap<SymbolId, IndexSet<SymbolId>> {
+    let mut captures: HashMap<SymbolId, IndexSet<SymbolId>> = functions
+        .iter()
+        .map(|symbol_id| (*symbol_id, IndexSet::new()))
+        .collect();
+
+    // Recursive functions capture themselves, so this needs to run until it reaches a fixed point.
+    loop {
+        let mut changed = false;
+
+        for symbol_id in functions {
+            let Symbol::Function {
+                resolved_body: Some(body),
+                scope: Some(scope),
+                ..
+            } = db.symbol(*symbol_id)
+            else {
+                continue;
+            };
+
+            let mut references = IndexSet::new();
+            collect_references(db, &captures, inlined, body, &mut references);
+
+            for reference in references {
+                if scope.defined_symbols().contains(&reference) {
+                    continue;
+                }
+                if captures.get_mut(symbol_id).unwrap().insert(reference) {
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break captures;
+        }
+    }
+}
+
+fn collect_references(
+    db: &Database,
+    captures: &HashMap<SymbolId, IndexSet<SymbolId>>,
+    inlined: &HashSet<SymbolId>,
+    hir: &Hir,
+    references: &mut IndexSet<SymbolId>,
+) {
+    match hir {
+        Hir::Int(_) | Hir::String(_) | Hir::Error => {}
+        Hir::Symbol(symbol_id) => {
+            references.insert(*symbol_id);
+        }
+        Hir::BinOp { lhs, rhs, .. } => {
+            collect_references(db, captures, inlined, lhs, references);
+            collect_references(db, captures, inlined, rhs, references);
+        }
+        Hir::Call {
+            value, arguments, ..
+        } => {
+            match value.as_ref() {
+                Hir::Symbol(symbol_id) if captures.contains_key(symbol_id) => {
+                    if !inlined.contains(symbol_id) {
+                        references.insert(*symbol_id);
+                    }
+                    references.extend(captures[symbol_id].iter().copied());
+                }
+                value => collect_references(db, captures, inlined, value, references),
+            }
+
+            for argument in arguments {
+                collect_references(db, captures, inlined, argument, references);
+            }
+        }
+        Hir::List(items) => {
+            for item in items {
+                collect_references(db, captures, inlined, item, references);
+            }
+        }
+        Hir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            collect_references(db, captures, inlined, condition, references);
+            collect_references(db, captures, inlined, then_branch, references);
+            collect_references(db, captures, inlined, else_branch, references);
+        }
+        Hir::Block { scope, value } => {
+            collect_references(db, captures, inlined, value, references);
+            for (_, value) in local_variables(db, scope) {
+                collect_references(db, captures, inlined, value, references);
+            }
+
+            // Variables are defined by the block, so they're never captured.
+            for symbol_id in scope.defined_symbols() {
+                references.shift_remove(symbol_id);
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::{error, fmt};
+
+use rue_error::TextRange;
+
+/// Why a program couldn't be compiled, even though the HIR didn't report any errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileErrorKind {
+    /// There is no entry point with the given name, such as a program without `main`.
+    MissingEntry(String),
+    /// The entry point is a symbol other than a function.
+    InvalidEntry,
+    /// A function without a body was reached, which only happens if it had errors.
+    UnresolvedFunction,
+    /// A symbol was referenced where it isn't in scope, such as a capture that wasn't
+    /// resolved.
+    UnresolvedSymbol,
+    /// A function was called without a value for one of its parameters.
+    MissingArgument,
+    /// An expression that failed to type check, which only exists in programs with errors.
+    ErrorExpression,
+    /// The CLVM allocator ran out of space for the program's atoms or pairs.
+    Allocator(String),
+    /// The program couldn't be serialized.
+    Serialize(String),
+}
+
+impl fmt::Display for CompileErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingEntry(name) => write!(f, "there is no function named `{name}`"),
+            Self::InvalidEntry => write!(f, "the entry point is not a function"),
+            Self::UnresolvedFunction => write!(f, "a function without a body can't be compiled"),
+            Self::UnresolvedSymbol => write!(f, "a symbol was referenced outside of its scope"),
+            Self::MissingArgument => write!(f, "a function was called with too few arguments"),
+            Self::ErrorExpression => {
+                write!(f, "an expression with errors can't be compiled")
+            }
+            Self::Allocator(message) => write!(f, "could not allocate the program: {message}"),
+            Self::Serialize(message) => write!(f, "could not serialize the program: {message}"),
+        }
+    }
+}
+
+/// A compile error, along with the source range of the innermost expression that was
+/// being compiled when it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub kind: CompileErrorKind,
+    pub range: Option<TextRange>,
+}
+
+impl CompileError {
+    pub fn new(kind: CompileErrorKind) -> Self {
+        Self { kind, range: None }
+    }
+
+    /// Sets the range if the error doesn't have one yet, so that the innermost
+    /// expression is reported as errors are propagated outwards.
+    pub fn within(mut self, range: TextRange) -> Self {
+        self.range.get_or_insert(range);
+        self
+    }
+}
+
+impl From<CompileErrorKind> for CompileError {
+    fn from(kind: CompileErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.range {
+            Some(range) => write!(f, "{} in the expression at {range}", self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl error::Error for CompileError {}
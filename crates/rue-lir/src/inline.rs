@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use rue_hir::{local_variables, Database, Hir, Symbol, SymbolId};
+
+use crate::{layout::depth, Lir};
+
+/// Functions with at most this many HIR nodes in their body are always inlined.
+const MAX_INLINE_SIZE: usize = 8;
+
+/// Decides which functions should be inlined at every call site.
+///
+/// This includes functions marked `inline` and functions that are only called once, as well
+/// as functions whose bodies are small enough that a call would cost more than the body itself
+/// if `inline_small` is set. Recursive functions and the entrypoint are never inlined.
+pub(crate) fn inline_candidates(
+    db: &Database,
+    functions: &[SymbolId],
+    main: SymbolId,
+    inline_marked: bool,
+    inline_small: bool,
+) -> HashSet<SymbolId> {
+    if !inline_marked && !inline_small {
+        return HashSet::new();
+    }
+
+    let mut references = HashMap::new();
+    let mut calls = HashMap::new();
+    let mut callees = HashMap::new();
+
+    for symbol_id in functions {
+        if let Symbol::Function {
+            resolved_body: Some(body),
+            ..
+        } = db.symbol(*symbol_id)
+        {
+            let mut function_callees = HashSet::new();
+            count_references(db, body, &mut references, &mut calls, &mut function_callees);
+            callees.insert(*symbol_id, function_callees);
+        }
+    }
+
+    functions
+        .iter()
+        .copied()
+        .filter(|symbol_id| {
+            let Symbol::Function {
+                resolved_body: Some(body),
+                is_inline,
+                ..
+            } = db.symbol(*symbol_id)
+            else {
+                return false;
+            };
+
+            if *symbol_id == main || is_recursive(*symbol_id, &callees) {
+                return false;
+            }
+
+            let single_use =
+                references.get(symbol_id) == Some(&1) && calls.get(symbol_id) == Some(&1);
+
+            (inline_marked && (*is_inline || single_use))
+                || (inline_small && hir_size(db, body) <= MAX_INLINE_SIZE)
+        })
+        .collect()
+}
+
+/// Counts how many times a parameter is referenced in a function body.
+pub(crate) fn parameter_uses(db: &Database, hir: &Hir, symbol_id: SymbolId) -> usize {
+    match hir {
+        Hir::Int(_) | Hir::String(_) | Hir::Error => 0,
+        Hir::Symbol(id) => (*id == symbol_id) as usize,
+        Hir::BinOp { lhs, rhs, .. } => {
+            parameter_uses(db, lhs, symbol_id) + parameter_uses(db, rhs, symbol_id)
+        }
+        Hir::Call {
+            value, arguments, ..
+        } => {
+            parameter_uses(db, value, symbol_id)
+                + arguments
+                    .iter()
+                    .map(|argument| parameter_uses(db, argument, symbol_id))
+                    .sum::<usize>()
+        }
+        Hir::List(items) => items
+            .iter()
+            .map(|item| parameter_uses(db, item, symbol_id))
+            .sum(),
+        Hir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            parameter_uses(db, condition, symbol_id)
+                + parameter_uses(db, then_branch, symbol_id)
+                + parameter_uses(db, else_branch, symbol_id)
+        }
+        Hir::Block { scope, value } => {
+            parameter_uses(db, value, symbol_id)
+                + local_variables(db, scope)
+                    .map(|(_, value)| parameter_uses(db, value, symbol_id))
+                    .sum::<usize>()
+        }
+    }
+}
+
+/// Counts how many times a parameter is referenced in a function body outside of the
+/// branches of an `if`, where it is evaluated every time the body is.
+pub(crate) fn unconditional_uses(db: &Database, hir: &Hir, symbol_id: SymbolId) -> usize {
+    match hir {
+        Hir::If { condition, .. } => unconditional_uses(db, condition, symbol_id),
+        Hir::Int(_) | Hir::String(_) | Hir::Error => 0,
+        Hir::Symbol(id) => (*id == symbol_id) as usize,
+        Hir::BinOp { lhs, rhs, .. } => {
+            unconditional_uses(db, lhs, symbol_id) + unconditional_uses(db, rhs, symbol_id)
+        }
+        Hir::Call {
+            value, arguments, ..
+        } => {
+            unconditional_uses(db, value, symbol_id)
+                + arguments
+                    .iter()
+                    .map(|argument| unconditional_uses(db, argument, symbol_id))
+                    .sum::<usize>()
+        }
+        Hir::List(items) => items
+            .iter()
+            .map(|item| unconditional_uses(db, item, symbol_id))
+            .sum(),
+        Hir::Block { scope, value } => {
+            unconditional_uses(db, value, symbol_id)
+                + local_variables(db, scope)
+                    .map(|(_, value)| unconditional_uses(db, value, symbol_id))
+                    .sum::<usize>()
+        }
+    }
+}
+
+/// Whether an argument is cheap enough to be duplicated rather than bound once.
+pub(crate) fn is_trivial(lir: &Lir) -> bool {
+    matches!(
+        lir.unspanned(),
+        Lir::Int(_) | Lir::String(_) | Lir::Path(_) | Lir::Quote(_)
+    )
+}
+
+/// Rewrites the environment paths in a value so that it can be evaluated in a new
+/// environment, where the original environment is found at the path `tail`.
+pub(crate) fn shift_paths(lir: Lir, tail: usize) -> Lir {
+    let shift = |lir: Box<Lir>| Box::new(shift_paths(*lir, tail));
+    let shift_all = |lirs: Vec<Lir>| lirs.into_iter().map(|lir| shift_paths(lir, tail)).collect();
+
+    match lir {
+        Lir::Int(_) | Lir::String(_) | Lir::Quote(_) => lir,
+        Lir::Path(path) => {
+            let depth = depth(tail);
+            Lir::Path((path << depth) | (tail - (1 << depth)))
+        }
+        Lir::Add(args) => Lir::Add(shift_all(args)),
+        Lir::Sub(args) => Lir::Sub(shift_all(args)),
+        Lir::Mul(args) => Lir::Mul(shift_all(args)),
+        Lir::Div(args) => Lir::Div(shift_all(args)),
+        Lir::Lt(lhs, rhs) => Lir::Lt(shift(lhs), shift(rhs)),
+        Lir::Gt(lhs, rhs) => Lir::Gt(shift(lhs), shift(rhs)),
+        Lir::GtBytes(lhs, rhs) => Lir::GtBytes(shift(lhs), shift(rhs)),
+        Lir::Cons(first, rest) => Lir::Cons(shift(first), shift(rest)),
+        Lir::Environment { value, environment } => Lir::Environment {
+            value: shift(value),
+            environment: shift(environment),
+        },
+        Lir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => Lir::If {
+            condition: shift(condition),
+            then_branch: shift(then_branch),
+            else_branch: shift(else_branch),
+        },
+        Lir::Span(range, value) => Lir::Span(range, shift(value)),
+    }
+}
+
+fn count_references(
+    db: &Database,
+    hir: &Hir,
+    references: &mut HashMap<SymbolId, usize>,
+    calls: &mut HashMap<SymbolId, usize>,
+    callees: &mut HashSet<SymbolId>,
+) {
+    match hir {
+        Hir::Int(_) | Hir::String(_) | Hir::Error => {}
+        Hir::Symbol(symbol_id) => {
+            *references.entry(*symbol_id).or_default() += 1;
+            callees.insert(*symbol_id);
+        }
+        Hir::BinOp { lhs, rhs, .. } => {
+            count_references(db, lhs, references, calls, callees);
+            count_references(db, rhs, references, calls, callees);
+        }
+        Hir::Call {
+            value, arguments, ..
+        } => {
+            if let Hir::Symbol(symbol_id) = value.as_ref() {
+                *calls.entry(*symbol_id).or_default() += 1;
+            }
+            count_references(db, value, references, calls, callees);
+            for argument in arguments {
+                count_references(db, argument, references, calls, callees);
+            }
+        }
+        Hir::List(items) => {
+            for item in items {
+                count_references(db, item, references, calls, callees);
+            }
+        }
+        Hir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            count_references(db, condition, references, calls, callees);
+            count_references(db, then_branch, references, calls, callees);
+            count_references(db, else_branch, references, calls, callees);
+        }
+        Hir::Block { scope, value } => {
+            count_references(db, value, references, calls, callees);
+            for (_, value) in local_variables(db, scope) {
+                count_references(db, value, references, calls, callees);
+            }
+        }
+    }
+}
+
+fn is_recursive(symbol_id: SymbolId, callees: &HashMap<SymbolId, HashSet<SymbolId>>) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![symbol_id];
+
+    while let Some(current) = stack.pop() {
+        for callee in callees.get(&current).into_iter().flatten() {
+            if *callee == symbol_id {
+                return true;
+            }
+            if visited.insert(*callee) {
+                stack.push(*callee);
+            }
+        }
+    }
+
+    false
+}
+
+fn hir_size(db: &Database, hir: &Hir) -> usize {
+    1 + match hir {
+        Hir::Int(_) | Hir::String(_) | Hir::Symbol(_) | Hir::Error => 0,
+        Hir::BinOp { lhs, rhs, .. } => hir_size(db, lhs) + hir_size(db, rhs),
+        Hir::Call {
+            value, arguments, ..
+        } => {
+            hir_size(db, value)
+                + arguments
+                    .iter()
+                    .map(|argument| hir_size(db, argument))
+                    .sum::<usize>()
+        }
+        Hir::List(items) => items.iter().map(|item| hir_size(db, item)).sum::<usize>(),
+        Hir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => hir_size(db, condition) + hir_size(db, then_branch) + hir_size(db, else_branch),
+        Hir::Block { scope, value } => {
+            hir_size(db, value)
+                + local_variables(db, scope)
+                    .map(|(_, value)| hir_size(db, value))
+                    .sum::<usize>()
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use indexmap::{IndexMap, IndexSet};
+use rue_hir::{local_variables, Database, Hir, SymbolId};
+
+use crate::Lir;
+
+/// Describes where each symbol is placed in an environment.
+///
+/// A list layout places each value one level deeper than the last, which is how arguments
+/// are normally passed. A tree layout instead balances the values, so that the path to each
+/// one is logarithmic in the number of values rather than linear.
+#[derive(Debug, Clone)]
+pub(crate) struct Layout {
+    symbols: Vec<SymbolId>,
+    is_tree: bool,
+}
+
+impl Layout {
+    pub fn list(symbols: Vec<SymbolId>) -> Self {
+        Self {
+            symbols,
+            is_tree: false,
+        }
+    }
+
+    /// Creates a balanced layout, where the most used symbols are given the shortest paths.
+    pub fn tree(mut symbols: Vec<SymbolId>, uses: &HashMap<SymbolId, usize>) -> Self {
+        symbols.sort_by_key(|symbol_id| std::cmp::Reverse(uses.get(symbol_id).copied()));
+        Self {
+            symbols,
+            is_tree: true,
+        }
+    }
+
+    pub fn symbols(&self) -> &[SymbolId] {
+        &self.symbols
+    }
+
+    /// Computes the path of each symbol, given the path to the environment itself.
+    /// If the environment has a tail, the path to the tail is returned as well.
+    pub fn paths(&self, root: usize, has_tail: bool) -> (IndexMap<SymbolId, usize>, usize) {
+        let mut paths = IndexMap::new();
+
+        if !self.is_tree {
+            let mut path = root;
+            for symbol_id in self.symbols.iter() {
+                paths.insert(*symbol_id, first(path));
+                path = rest(path);
+            }
+            return (paths, path);
+        }
+
+        if self.symbols.is_empty() {
+            return (paths, root);
+        }
+
+        let (tree_root, tail) = if has_tail {
+            (first(root), rest(root))
+        } else {
+            (root, root)
+        };
+
+        for (symbol_id, path) in self
+            .symbols
+            .iter()
+            .zip(tree_paths(self.symbols.len(), tree_root))
+        {
+            paths.insert(*symbol_id, path);
+        }
+
+        (paths, tail)
+    }
+
+    /// Constructs the environment from the value of each symbol, in the same order as the layout.
+    pub fn construct(&self, values: Vec<Lir>, tail: Option<Lir>) -> Lir {
+        assert_eq!(values.len(), self.symbols.len());
+
+        if !self.is_tree {
+            return values
+                .into_iter()
+                .rev()
+                .fold(tail.unwrap_or(Lir::Int(0.into())), |rest, value| {
+                    Lir::Cons(Box::new(value), Box::new(rest))
+                });
+        }
+
+        if values.is_empty() {
+            return tail.unwrap_or(Lir::Int(0.into()));
+        }
+
+        let paths = tree_paths(values.len(), 1);
+        let leaves = values.len();
+        let mut values: HashMap<usize, Lir> = paths.into_iter().zip(values).collect();
+        let tree = build_tree(&mut values, leaves, 1);
+
+        match tail {
+            Some(tail) => Lir::Cons(Box::new(tree), Box::new(tail)),
+            None => tree,
+        }
+    }
+}
+
+/// Counts how many times each symbol is needed by a function body, including the
+/// captures that have to be forwarded when calling other functions.
+pub(crate) fn count_uses(
+    db: &Database,
+    hir: &Hir,
+    captures: &HashMap<SymbolId, IndexSet<SymbolId>>,
+    uses: &mut HashMap<SymbolId, usize>,
+) {
+    match hir {
+        Hir::Int(_) | Hir::String(_) | Hir::Error => {}
+        Hir::Symbol(symbol_id) => {
+            *uses.entry(*symbol_id).or_default() += 1;
+        }
+        Hir::BinOp { lhs, rhs, .. } => {
+            count_uses(db, lhs, captures, uses);
+            count_uses(db, rhs, captures, uses);
+        }
+        Hir::Call {
+            value, arguments, ..
+        } => {
+            if let Hir::Symbol(symbol_id) = value.as_ref() {
+                for captured_symbol in captures.get(symbol_id).into_iter().flatten() {
+                    *uses.entry(*captured_symbol).or_default() += 1;
+                }
+            }
+            count_uses(db, value, captures, uses);
+            for argument in arguments {
+                count_uses(db, argument, captures, uses);
+            }
+        }
+        Hir::List(items) => {
+            for item in items {
+                count_uses(db, item, captures, uses);
+            }
+        }
+        Hir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            count_uses(db, condition, captures, uses);
+            count_uses(db, then_branch, captures, uses);
+            count_uses(db, else_branch, captures, uses);
+        }
+        Hir::Block { scope, value } => {
+            count_uses(db, value, captures, uses);
+            for (_, value) in local_variables(db, scope) {
+                count_uses(db, value, captures, uses);
+            }
+        }
+    }
+}
+
+/// The path to the first value of the pair at `path`.
+pub(crate) fn first(path: usize) -> usize {
+    path + (1 << depth(path))
+}
+
+/// The path to the rest of the pair at `path`.
+pub(crate) fn rest(path: usize) -> usize {
+    path + (2 << depth(path))
+}
+
+pub(crate) fn depth(path: usize) -> u32 {
+    usize::BITS - 1 - path.leading_zeros()
+}
+
+/// The paths of each leaf in a balanced tree, with the shallowest leaves first.
+fn tree_paths(leaves: usize, root: usize) -> Vec<usize> {
+    let mut paths = Vec::new();
+    collect_tree_paths(leaves, root, &mut paths);
+    paths.sort_by_key(|path| depth(*path));
+    paths
+}
+
+fn collect_tree_paths(leaves: usize, path: usize, paths: &mut Vec<usize>) {
+    if leaves == 1 {
+        paths.push(path);
+        return;
+    }
+    let left = leaves.div_ceil(2);
+    collect_tree_paths(left, first(path), paths);
+    collect_tree_paths(leaves - left, rest(path), paths);
+}
+
+fn build_tree(values: &mut HashMap<usize, Lir>, leaves: usize, path: usize) -> Lir {
+    if leaves == 1 {
+        return values.remove(&path).unwrap();
+    }
+    let left = leaves.div_ceil(2);
+    Lir::Cons(
+        Box::new(build_tree(values, left, first(path))),
+        Box::new(build_tree(values, leaves - left, rest(path))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pair_paths() {
+        assert_eq!(first(1), 2);
+        assert_eq!(rest(1), 3);
+        assert_eq!(first(3), 5);
+        assert_eq!(rest(3), 7);
+        assert_eq!(first(2), 4);
+        assert_eq!(rest(2), 6);
+    }
+
+    #[test]
+    fn balanced_tree_paths() {
+        assert_eq!(tree_paths(1, 1), vec![1]);
+        assert_eq!(tree_paths(2, 1), vec![2, 3]);
+        assert_eq!(tree_paths(3, 1), vec![3, 4, 6]);
+        assert_eq!(tree_paths(4, 1), vec![4, 6, 5, 7]);
+        assert!(tree_paths(10, 1).iter().all(|path| depth(*path) <= 4));
+    }
+}
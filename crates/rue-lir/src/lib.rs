@@ -1,108 +1,307 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
 use num_bigint::BigInt;
-use rue_hir::{BinaryOp, Database, Hir, Scope, Symbol, SymbolId};
+use rue_hir::{local_variables, reachable_symbols, BinOp, Database, Hir, Scope, Symbol, SymbolId};
 
+mod captures;
+mod error;
+mod inline;
+mod layout;
 mod lir;
+mod optimize;
+mod passes;
 
+pub use error::*;
 pub use lir::*;
+pub use optimize::*;
+pub use passes::*;
+
+use captures::compute_captures;
+use inline::{inline_candidates, is_trivial, parameter_uses, shift_paths, unconditional_uses};
+use layout::{count_uses, Layout};
+
+pub fn lower(db: Database, scope: Scope, passes: &PassManager) -> Result<Lir, CompileError> {
+    let main = scope
+        .lookup_symbol("main")
+        .ok_or_else(|| CompileErrorKind::MissingEntry("main".to_string()))?;
+    lower_entry(db, &scope, main, passes)
+}
 
-pub fn lower(db: Database, scope: Scope) -> Option<Lir> {
-    let lowerer = Lowerer::new(db, scope);
-    lowerer.lower_main()
+/// Lowers the program as if the given function was `main`, which is how tests are
+/// compiled into their own programs.
+pub fn lower_entry(
+    db: Database,
+    scope: &Scope,
+    entry: SymbolId,
+    passes: &PassManager,
+) -> Result<Lir, CompileError> {
+    let lowerer = Lowerer::new(db, scope, entry, passes);
+    lowerer.lower_main(entry)
 }
 
 struct Lowerer {
-    scopes: Vec<(Scope, IndexMap<SymbolId, Lir>)>,
     db: Database,
+    optimize_environment: bool,
+    captures: HashMap<SymbolId, IndexSet<SymbolId>>,
+    inlined: HashSet<SymbolId>,
+    layouts: HashMap<SymbolId, Layout>,
+    symbol_tables: Vec<IndexMap<SymbolId, Lir>>,
 }
 
 impl Lowerer {
-    fn new(db: Database, scope: Scope) -> Self {
-        let mut result = Self {
-            scopes: Vec::new(),
+    fn new(db: Database, scope: &Scope, main: SymbolId, passes: &PassManager) -> Self {
+        // Unreachable functions are never lowered, so they can't affect any of the analyses.
+        let reachable = reachable_symbols(&db, &[main]);
+        let functions = scope
+            .defined_symbols()
+            .iter()
+            .copied()
+            .filter(|symbol_id| reachable.contains(symbol_id))
+            .filter(|symbol_id| matches!(db.symbol(*symbol_id), Symbol::Function { .. }))
+            .collect_vec();
+
+        let inlined = inline_candidates(
+            &db,
+            &functions,
+            main,
+            passes.is_enabled(Pass::Inline),
+            passes.is_enabled(Pass::InlineSmall),
+        );
+
+        let mut lowerer = Self {
+            captures: compute_captures(&db, &functions, &inlined),
             db,
+            optimize_environment: passes.is_enabled(Pass::EnvironmentLayout),
+            inlined,
+            layouts: HashMap::new(),
+            symbol_tables: Vec::new(),
         };
-        result.push_scope(scope);
-        result
-    }
-
-    fn build_environment(&mut self) -> Vec<Lir> {
-        let mut environment = Vec::new();
-
-        for symbol_id in self.scope().defined_symbols().clone() {
-            if self.scope().used_symbols().contains(&symbol_id) {
-                match self.db.symbol(symbol_id) {
-                    Symbol::Parameter { .. } => {}
-                    Symbol::Builtin { .. } => {}
-                    Symbol::Variable { value, .. } => {
-                        environment.push(self.lower_hir(&value.clone()));
-                    }
-                    Symbol::Function {
-                        resolved_body,
-                        scope,
-                        ..
-                    } => {
-                        let value = self.lower_function(
-                            resolved_body.as_ref().unwrap().clone(),
-                            scope.as_ref().unwrap().clone(),
-                        );
-                        environment.push(Lir::Quote(Box::new(value)));
-                    }
-                }
-            }
-        }
 
-        self.pop_scope();
+        // Callers and callees need to agree on where each argument is placed.
+        for symbol_id in functions {
+            let Symbol::Function {
+                scope: Some(scope), ..
+            } = lowerer.db.symbol(symbol_id)
+            else {
+                continue;
+            };
+
+            let uses = lowerer.function_uses(symbol_id);
 
-        environment
+            let mut symbols = lowerer.captures[&symbol_id].iter().copied().collect_vec();
+            symbols.extend(lowerer.parameters(scope));
+
+            let layout = lowerer.layout(symbols, &uses);
+            lowerer.layouts.insert(symbol_id, layout);
+        }
+
+        lowerer
     }
 
-    fn lower_main(mut self) -> Option<Lir> {
-        let main = self.scope().lookup_symbol("main")?;
+    fn lower_main(mut self, main: SymbolId) -> Result<Lir, CompileError> {
+        let scope = match self.db.symbol(main) {
+            Symbol::Function {
+                resolved_body: Some(_),
+                scope: Some(scope),
+                ..
+            } => scope,
+            Symbol::Function { .. } => return Err(CompileErrorKind::UnresolvedFunction.into()),
+            _ => return Err(CompileErrorKind::InvalidEntry.into()),
+        };
 
-        if let Symbol::Function {
-            resolved_body: Some(resolved_body),
-            scope: Some(scope),
-            ..
-        } = self.db.symbol(main)
-        {
-            let body = self.lower_function(resolved_body.clone(), scope.clone());
-            Some(Lir::Environment {
-                value: Box::new(Lir::Quote(Box::new(body))),
-                arguments: self.build_environment(),
-                rest: Some(Box::new(Lir::Path(1))),
-            })
+        let uses = self.function_uses(main);
+
+        // The program's environment is the functions that main captures, followed by the
+        // values that were curried in and then the solution. Curried parameters come first,
+        // so all of the parameters of main can be read from that list in order.
+        let captures = self.captures[&main].iter().copied().collect_vec();
+        let parameters = self.parameters(scope);
+        let layout = self.layout(captures.clone(), &uses);
+
+        let (mut paths, tail) = if captures.is_empty() {
+            (IndexMap::new(), 1)
         } else {
-            None
+            layout.paths(1, true)
+        };
+        paths.extend(Layout::list(parameters).paths(tail, true).0);
+
+        let symbol_table = paths
+            .into_iter()
+            .map(|(symbol_id, path)| (symbol_id, Lir::Path(path)))
+            .collect();
+
+        let body = self.lower_body(main, symbol_table)?;
+
+        if captures.is_empty() {
+            return Ok(body);
         }
+
+        let values = layout
+            .symbols()
+            .to_vec()
+            .into_iter()
+            .map(|symbol_id| Ok(Lir::Quote(Box::new(self.lower_function(symbol_id)?))))
+            .collect::<Result<_, CompileError>>()?;
+
+        Ok(Lir::Environment {
+            value: Box::new(Lir::Quote(Box::new(body))),
+            environment: Box::new(layout.construct(values, Some(Lir::Path(1)))),
+        })
     }
 
-    fn lower_function(&mut self, body: Hir, scope: Scope) -> Lir {
-        self.push_scope(scope);
+    fn lower_function(&mut self, symbol_id: SymbolId) -> Result<Lir, CompileError> {
+        let (paths, _) = self.layout_of(symbol_id)?.paths(1, false);
+
+        let symbol_table = paths
+            .into_iter()
+            .map(|(symbol_id, path)| (symbol_id, Lir::Path(path)))
+            .collect();
+
+        self.lower_body(symbol_id, symbol_table)
+    }
+
+    fn lower_body(
+        &mut self,
+        symbol_id: SymbolId,
+        symbol_table: IndexMap<SymbolId, Lir>,
+    ) -> Result<Lir, CompileError> {
+        let Symbol::Function {
+            resolved_body: Some(body),
+            ..
+        } = self.db.symbol(symbol_id)
+        else {
+            return Err(CompileErrorKind::UnresolvedFunction.into());
+        };
+
+        let body = body.clone();
+
+        self.symbol_tables.push(symbol_table);
         let body = self.lower_hir(&body);
+        self.symbol_tables.pop();
+        body
+    }
 
-        Lir::Environment {
-            value: Box::new(Lir::Quote(Box::new(body))),
-            arguments: self.build_environment(),
-            rest: Some(Box::new(Lir::Path(1))),
+    /// Binds the variables of a block where it's evaluated, rather than at the start of
+    /// the function, so that they aren't computed unless the block is.
+    fn lower_block(&mut self, scope: &Scope, value: &Hir) -> Result<Lir, CompileError> {
+        let locals = self.locals(scope);
+
+        let mut uses = HashMap::new();
+        count_uses(&self.db, value, &self.captures, &mut uses);
+        for (_, local) in locals.iter().flatten() {
+            count_uses(&self.db, local, &self.captures, &mut uses);
         }
+
+        self.lower_locals(&locals, value, &uses)
     }
 
-    fn lower_hir(&mut self, hir: &Hir) -> Lir {
+    /// Binds each layer of local variables in turn, then lowers `body` with all of them
+    /// in scope. The values are lowered in the current symbol table.
+    fn lower_locals(
+        &mut self,
+        locals: &[Vec<(SymbolId, Hir)>],
+        body: &Hir,
+        uses: &HashMap<SymbolId, usize>,
+    ) -> Result<Lir, CompileError> {
+        let Some((layer, rest)) = locals.split_first() else {
+            return self.lower_hir(body);
+        };
+
+        let bindings = layer
+            .iter()
+            .map(|(symbol_id, value)| Ok((*symbol_id, self.lower_hir(value)?)))
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        let symbol_table = self.symbol_table().clone();
+
+        self.bind(bindings, uses, symbol_table, |lowerer| {
+            lowerer.lower_locals(rest, body, uses)
+        })
+    }
+
+    /// Extends the environment with the values of `bindings`, and lowers the rest of
+    /// the program inside of it with `lower`.
+    fn bind(
+        &mut self,
+        bindings: Vec<(SymbolId, Lir)>,
+        uses: &HashMap<SymbolId, usize>,
+        symbol_table: IndexMap<SymbolId, Lir>,
+        lower: impl FnOnce(&mut Self) -> Result<Lir, CompileError>,
+    ) -> Result<Lir, CompileError> {
+        if bindings.is_empty() {
+            self.symbol_tables.push(symbol_table);
+            let body = lower(self);
+            self.symbol_tables.pop();
+            return body;
+        }
+
+        let layout = self.layout(
+            bindings.iter().map(|(symbol_id, _)| *symbol_id).collect(),
+            uses,
+        );
+        let (paths, tail) = layout.paths(1, true);
+
+        let mut bound_table: IndexMap<SymbolId, Lir> = paths
+            .into_iter()
+            .map(|(symbol_id, path)| (symbol_id, Lir::Path(path)))
+            .collect();
+
+        for (symbol_id, value) in symbol_table {
+            bound_table.insert(symbol_id, shift_paths(value, tail));
+        }
+
+        self.symbol_tables.push(bound_table);
+        let body = lower(self)?;
+        self.symbol_tables.pop();
+
+        let mut bindings: HashMap<SymbolId, Lir> = bindings.into_iter().collect();
+        let values = layout
+            .symbols()
+            .iter()
+            .map(|symbol_id| bindings.remove(symbol_id).unwrap())
+            .collect();
+
+        Ok(Lir::Environment {
+            value: Box::new(Lir::Quote(Box::new(body))),
+            environment: Box::new(layout.construct(values, Some(Lir::Path(1)))),
+        })
+    }
+
+    fn lower_hir(&mut self, hir: &Hir) -> Result<Lir, CompileError> {
         match hir {
-            Hir::Int(value) => self.lower_int(value),
-            Hir::String(value) => self.lower_string(value),
+            Hir::Int(value) => Ok(self.lower_int(value)),
+            Hir::String(value) => Ok(self.lower_string(value)),
             Hir::Symbol(symbol_id) => self.lower_symbol(*symbol_id),
-            Hir::BinOp { op, lhs, rhs } => self.lower_bin_op(*op, lhs, rhs),
-            Hir::Call { value, arguments } => self.lower_call(value, arguments),
+            Hir::BinOp {
+                op,
+                lhs,
+                rhs,
+                range,
+            } => {
+                let value = self
+                    .lower_bin_op(*op, lhs, rhs)
+                    .map_err(|error| error.within(*range))?;
+                Ok(Lir::Span(*range, Box::new(value)))
+            }
+            Hir::Call {
+                value,
+                arguments,
+                range,
+            } => {
+                let value = self
+                    .lower_call(value, arguments)
+                    .map_err(|error| error.within(*range))?;
+                Ok(Lir::Span(*range, Box::new(value)))
+            }
+            Hir::List(items) => self.lower_list(items),
             Hir::If {
                 condition,
                 then_branch,
                 else_branch,
             } => self.lower_if(condition, then_branch, else_branch),
+            Hir::Block { scope, value } => self.lower_block(scope, value),
+            Hir::Error => Err(CompileErrorKind::ErrorExpression.into()),
         }
     }
 
@@ -114,123 +313,216 @@ impl Lowerer {
         Lir::String(value.to_string())
     }
 
-    fn lower_symbol(&mut self, symbol_id: SymbolId) -> Lir {
-        self.symbol_table().get(&symbol_id).unwrap().clone()
+    fn lower_symbol(&mut self, symbol_id: SymbolId) -> Result<Lir, CompileError> {
+        self.symbol_table()
+            .get(&symbol_id)
+            .cloned()
+            .ok_or_else(|| CompileErrorKind::UnresolvedSymbol.into())
     }
 
-    fn lower_bin_op(&mut self, op: BinaryOp, lhs: &Hir, rhs: &Hir) -> Lir {
-        let lhs = self.lower_hir(lhs);
-        let rhs = self.lower_hir(rhs);
-        match op {
-            BinaryOp::Add => Lir::Add(vec![lhs, rhs]),
-            BinaryOp::Sub => Lir::Sub(vec![lhs, rhs]),
-            BinaryOp::Mul => Lir::Mul(vec![lhs, rhs]),
-            BinaryOp::Div => Lir::Div(vec![lhs, rhs]),
-            BinaryOp::Lt => Lir::Lt(Box::new(lhs), Box::new(rhs)),
-            BinaryOp::Gt => Lir::Gt(Box::new(lhs), Box::new(rhs)),
-        }
+    fn lower_bin_op(&mut self, op: BinOp, lhs: &Hir, rhs: &Hir) -> Result<Lir, CompileError> {
+        let lhs = self.lower_hir(lhs)?;
+        let rhs = self.lower_hir(rhs)?;
+        Ok(match op {
+            BinOp::Add => Lir::Add(vec![lhs, rhs]),
+            BinOp::Sub => Lir::Sub(vec![lhs, rhs]),
+            BinOp::Mul => Lir::Mul(vec![lhs, rhs]),
+            BinOp::Div => Lir::Div(vec![lhs, rhs]),
+            BinOp::Lt => Lir::Lt(Box::new(lhs), Box::new(rhs)),
+            BinOp::Gt => Lir::Gt(Box::new(lhs), Box::new(rhs)),
+            BinOp::LtBytes => Lir::GtBytes(Box::new(rhs), Box::new(lhs)),
+            BinOp::GtBytes => Lir::GtBytes(Box::new(lhs), Box::new(rhs)),
+        })
     }
 
-    fn lower_call(&mut self, value: &Hir, arguments: &[Hir]) -> Lir {
+    fn lower_call(&mut self, value: &Hir, arguments: &[Hir]) -> Result<Lir, CompileError> {
         if let Hir::Symbol(symbol_id) = value {
             match self.db.symbol(*symbol_id) {
-                Symbol::Function {
-                    scope: Some(scope), ..
-                } => {
-                    let mut environment = Vec::new();
-
-                    for capture in scope.captured_symbols() {
-                        environment.push(self.lower_symbol(capture));
-                    }
-
-                    for argument in arguments {
-                        environment.push(self.lower_hir(argument));
-                    }
-
-                    return Lir::Environment {
-                        value: Box::new(self.lower_hir(value)),
-                        arguments: environment,
-                        rest: None,
-                    };
+                Symbol::Function { .. } if self.inlined.contains(symbol_id) => {
+                    return self.lower_inline_call(*symbol_id, arguments);
+                }
+                Symbol::Function { .. } => {
+                    let mut arguments = arguments
+                        .iter()
+                        .map(|argument| self.lower_hir(argument).map(Some))
+                        .collect::<Result<Vec<_>, CompileError>>()?;
+
+                    let layout = self.layout_of(*symbol_id)?.clone();
+                    let values = layout
+                        .symbols()
+                        .iter()
+                        .map(|symbol_id| match self.db.symbol(*symbol_id) {
+                            Symbol::Parameter { index, .. } => arguments
+                                .get_mut(*index)
+                                .and_then(Option::take)
+                                .ok_or_else(|| CompileErrorKind::MissingArgument.into()),
+                            _ => self.lower_symbol(*symbol_id),
+                        })
+                        .collect::<Result<_, CompileError>>()?;
+
+                    return Ok(Lir::Environment {
+                        value: Box::new(self.lower_hir(value)?),
+                        environment: Box::new(layout.construct(values, None)),
+                    });
                 }
-                Symbol::Builtin {
-                    param_types,
-                    return_type,
-                    resolver,
-                } => {
-                    todo!()
+                Symbol::Builtin { resolver, .. } => {
+                    let hir = resolver(arguments.to_vec());
+                    return self.lower_hir(&hir);
                 }
                 _ => {}
             }
         }
-        Lir::Environment {
-            value: Box::new(self.lower_hir(value)),
-            arguments: arguments
-                .into_iter()
-                .map(|argument| self.lower_hir(argument))
-                .collect(),
-            rest: None,
+
+        let environment = self.lower_list(arguments)?;
+
+        Ok(Lir::Environment {
+            value: Box::new(self.lower_hir(value)?),
+            environment: Box::new(environment),
+        })
+    }
+
+    fn lower_list(&mut self, items: &[Hir]) -> Result<Lir, CompileError> {
+        items
+            .iter()
+            .rev()
+            .try_fold(Lir::Int(0.into()), |rest, item| {
+                Ok(Lir::Cons(Box::new(self.lower_hir(item)?), Box::new(rest)))
+            })
+    }
+
+    fn lower_inline_call(
+        &mut self,
+        symbol_id: SymbolId,
+        arguments: &[Hir],
+    ) -> Result<Lir, CompileError> {
+        let Symbol::Function {
+            resolved_body: Some(body),
+            scope: Some(scope),
+            ..
+        } = self.db.symbol(symbol_id)
+        else {
+            return Err(CompileErrorKind::UnresolvedFunction.into());
+        };
+
+        let body = body.clone();
+        let parameters = self.parameters(scope);
+
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.lower_hir(argument))
+            .collect::<Result<Vec<_>, CompileError>>()?;
+
+        // Arguments which are used more than once (or never), or only inside an `if` branch,
+        // are bound to the environment so that they are still evaluated exactly once, and
+        // raise the same errors as they would in a call. The rest are substituted directly.
+        let mut bindings = Vec::new();
+        let uses = self.function_uses(symbol_id);
+        let mut symbol_table = IndexMap::new();
+
+        for (parameter, argument) in parameters.into_iter().zip(arguments) {
+            if is_trivial(&argument)
+                || (parameter_uses(&self.db, &body, parameter) == 1
+                    && unconditional_uses(&self.db, &body, parameter) == 1)
+            {
+                symbol_table.insert(parameter, argument);
+            } else {
+                bindings.push((parameter, argument));
+            }
         }
+
+        for captured_symbol in self.captures[&symbol_id].clone() {
+            let value = self.lower_symbol(captured_symbol)?;
+            symbol_table.insert(captured_symbol, value);
+        }
+
+        self.bind(bindings, &uses, symbol_table, |lowerer| {
+            lowerer.lower_hir(&body)
+        })
     }
 
-    fn lower_if(&mut self, condition: &Hir, then_branch: &Hir, else_branch: &Hir) -> Lir {
-        let condition = Box::new(self.lower_hir(condition));
-        let then_branch = Box::new(self.lower_hir(then_branch));
-        let else_branch = Box::new(self.lower_hir(else_branch));
-        Lir::If {
+    fn lower_if(
+        &mut self,
+        condition: &Hir,
+        then_branch: &Hir,
+        else_branch: &Hir,
+    ) -> Result<Lir, CompileError> {
+        let condition = Box::new(self.lower_hir(condition)?);
+        let then_branch = Box::new(self.lower_hir(then_branch)?);
+        let else_branch = Box::new(self.lower_hir(else_branch)?);
+        Ok(Lir::If {
             condition,
             then_branch,
             else_branch,
-        }
-    }
-
-    fn scope(&self) -> &Scope {
-        &self.scopes.last().unwrap().0
+        })
     }
 
-    fn scope_mut(&mut self) -> &mut Scope {
-        &mut self.scopes.last_mut().unwrap().0
+    /// Where a function expects its captures and parameters, which is only known for
+    /// functions that can be reached from the entry point.
+    fn layout_of(&self, symbol_id: SymbolId) -> Result<&Layout, CompileError> {
+        self.layouts
+            .get(&symbol_id)
+            .ok_or_else(|| CompileErrorKind::UnresolvedFunction.into())
     }
 
-    fn symbol_table(&self) -> &IndexMap<SymbolId, Lir> {
-        &self.scopes.last().unwrap().1
+    fn layout(&self, symbols: Vec<SymbolId>, uses: &HashMap<SymbolId, usize>) -> Layout {
+        if self.optimize_environment {
+            Layout::tree(symbols, uses)
+        } else {
+            Layout::list(symbols)
+        }
     }
 
-    fn push_scope(&mut self, scope: Scope) {
-        let mut symbol_table = IndexMap::new();
-        let mut path = 2;
-
-        for captured_symbol in scope.captured_symbols() {
-            symbol_table.insert(captured_symbol, Lir::Path(path));
-            path = path * 2 + 1;
+    /// The local variables of a block which are actually used, grouped into layers
+    /// such that each value only depends on variables in earlier layers.
+    fn locals(&self, scope: &Scope) -> Vec<Vec<(SymbolId, Hir)>> {
+        let mut layers: Vec<Vec<(SymbolId, Hir)>> = Vec::new();
+
+        for (symbol_id, value) in local_variables(&self.db, scope) {
+            let depends_on_last = layers.last().is_some_and(|layer| {
+                layer
+                    .iter()
+                    .any(|(local, _)| parameter_uses(&self.db, value, *local) > 0)
+            });
+
+            match layers.last_mut() {
+                Some(layer) if !depends_on_last => layer.push((symbol_id, value.clone())),
+                _ => layers.push(vec![(symbol_id, value.clone())]),
+            }
         }
 
-        let mut parameters = HashMap::new();
+        layers
+    }
 
-        for defined_symbol in scope.defined_symbols() {
-            if scope.used_symbols().contains(defined_symbol) {
-                if let Symbol::Parameter { index, .. } = self.db.symbol(*defined_symbol) {
-                    parameters.insert(*index, *defined_symbol);
-                } else {
-                    symbol_table.insert(*defined_symbol, Lir::Path(path));
-                    path = path * 2 + 1;
-                }
-            }
-        }
+    /// Counts how many times each symbol is needed by a function, including the values
+    /// of its local variables.
+    fn function_uses(&self, symbol_id: SymbolId) -> HashMap<SymbolId, usize> {
+        let mut uses = HashMap::new();
 
-        for index in parameters.keys().sorted() {
-            symbol_table.insert(*parameters.get(index).unwrap(), Lir::Path(path));
-            path = path * 2 + 1;
+        if let Symbol::Function {
+            resolved_body: Some(body),
+            ..
+        } = self.db.symbol(symbol_id)
+        {
+            count_uses(&self.db, body, &self.captures, &mut uses);
         }
 
-        self.scopes.push((scope, symbol_table));
+        uses
     }
 
-    fn pop_scope(&mut self) {
-        let (scope, _) = self.scopes.pop().unwrap();
+    fn parameters(&self, scope: &Scope) -> Vec<SymbolId> {
+        scope
+            .defined_symbols()
+            .iter()
+            .filter_map(|symbol_id| match self.db.symbol(*symbol_id) {
+                Symbol::Parameter { index, .. } => Some((*index, *symbol_id)),
+                _ => None,
+            })
+            .sorted()
+            .map(|(_, symbol_id)| symbol_id)
+            .collect()
+    }
 
-        for captured_symbol in scope.captured_symbols() {
-            self.scope_mut().mark_used(captured_symbol);
-        }
+    fn symbol_table(&self) -> &IndexMap<SymbolId, Lir> {
+        self.symbol_tables.last().unwrap()
     }
 }
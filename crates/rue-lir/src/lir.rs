@@ -1,4 +1,7 @@
+use std::fmt;
+
 use num_bigint::BigInt;
+use rue_error::TextRange;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Lir {
@@ -11,10 +14,11 @@ pub enum Lir {
     Div(Vec<Lir>),
     Lt(Box<Lir>, Box<Lir>),
     Gt(Box<Lir>, Box<Lir>),
+    GtBytes(Box<Lir>, Box<Lir>),
+    Cons(Box<Lir>, Box<Lir>),
     Environment {
         value: Box<Lir>,
-        arguments: Vec<Lir>,
-        rest: Option<Box<Lir>>,
+        environment: Box<Lir>,
     },
     If {
         condition: Box<Lir>,
@@ -22,4 +26,122 @@ pub enum Lir {
         else_branch: Box<Lir>,
     },
     Quote(Box<Lir>),
+    /// Marks the source range that a value was lowered from. It has no effect on the
+    /// program itself, and is only used to build a source map.
+    Span(TextRange, Box<Lir>),
+}
+
+impl Lir {
+    /// The value without any source ranges around it.
+    pub fn unspanned(&self) -> &Lir {
+        match self {
+            Self::Span(_, value) => value.unspanned(),
+            _ => self,
+        }
+    }
+
+    fn operator(&self) -> Option<(&'static str, Vec<&Lir>)> {
+        fn all(args: &[Lir]) -> Vec<&Lir> {
+            args.iter().collect()
+        }
+
+        Some(match self {
+            Self::Int(_) | Self::String(_) | Self::Path(_) => return None,
+            Self::Add(args) => ("+", all(args)),
+            Self::Sub(args) => ("-", all(args)),
+            Self::Mul(args) => ("*", all(args)),
+            Self::Div(args) => ("/", all(args)),
+            Self::Lt(lhs, rhs) => ("<", vec![lhs, rhs]),
+            Self::Gt(lhs, rhs) => (">", vec![lhs, rhs]),
+            Self::GtBytes(lhs, rhs) => (">s", vec![lhs, rhs]),
+            Self::Cons(first, rest) => ("c", vec![first, rest]),
+            Self::Environment { value, environment } => ("a", vec![value, environment]),
+            Self::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => ("if", vec![condition, then_branch, else_branch]),
+            Self::Quote(value) => ("q", vec![value]),
+            Self::Span(_, value) => return value.operator(),
+        })
+    }
+
+    fn write_pretty(&self, indent: usize, output: &mut String) {
+        let flat = self.to_string();
+        let Some((name, args)) = self.operator() else {
+            output.push_str(&flat);
+            return;
+        };
+
+        if indent + flat.len() <= MAX_LINE_WIDTH {
+            output.push_str(&flat);
+            return;
+        }
+
+        output.push('(');
+        output.push_str(name);
+        for arg in args {
+            output.push('\n');
+            output.push_str(&" ".repeat(indent + 2));
+            arg.write_pretty(indent + 2, output);
+        }
+        output.push(')');
+    }
+}
+
+const MAX_LINE_WIDTH: usize = 80;
+
+/// Writes the value as an s-expression, where paths are prefixed with `@`.
+/// The alternate form `{:#}` breaks long expressions over multiple lines.
+impl fmt::Display for Lir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            let mut output = String::new();
+            self.write_pretty(0, &mut output);
+            return write!(f, "{output}");
+        }
+
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            Self::String(value) => write!(f, "{value:?}"),
+            Self::Path(path) => write!(f, "@{path}"),
+            Self::Span(_, value) => write!(f, "{value}"),
+            _ => {
+                let (name, args) = self.operator().unwrap();
+                write!(f, "({name}")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let lir = Lir::If {
+            condition: Box::new(Lir::Lt(
+                Box::new(Lir::Path(2)),
+                Box::new(Lir::Int(10.into())),
+            )),
+            then_branch: Box::new(Lir::String("small".to_string())),
+            else_branch: Box::new(Lir::Add(vec![Lir::Path(2), Lir::Path(5)])),
+        };
+        assert_eq!(lir.to_string(), r#"(if (< @2 10) "small" (+ @2 @5))"#);
+        assert_eq!(format!("{lir:#}"), lir.to_string());
+
+        let long = Lir::Add(vec![lir.clone(), lir.clone(), lir]);
+        assert_eq!(
+            format!("{long:#}"),
+            r#"(+
+  (if (< @2 10) "small" (+ @2 @5))
+  (if (< @2 10) "small" (+ @2 @5))
+  (if (< @2 10) "small" (+ @2 @5)))"#
+        );
+    }
 }
@@ -0,0 +1,346 @@
+use num_bigint::BigInt;
+use num_traits::{One, Signed, Zero};
+
+use crate::Lir;
+
+/// Simplifies a program without changing its result.
+///
+/// Arithmetic and comparisons on constants are folded, nested additions and multiplications
+/// are flattened into a single variadic operator, identity operands are removed, and `if`
+/// expressions with a constant condition are replaced by the branch that would be taken.
+pub fn optimize(lir: Lir) -> Lir {
+    let optimize_box = |lir: Box<Lir>| Box::new(optimize(*lir));
+    let optimize_all = |lirs: Vec<Lir>| lirs.into_iter().map(optimize).collect::<Vec<_>>();
+
+    match lir {
+        Lir::Int(_) | Lir::String(_) | Lir::Path(_) => lir,
+        Lir::Add(args) => optimize_add(optimize_all(args)),
+        Lir::Sub(args) => optimize_sub(optimize_all(args)),
+        Lir::Mul(args) => optimize_mul(optimize_all(args)),
+        Lir::Div(args) => optimize_div(optimize_all(args)),
+        Lir::Lt(lhs, rhs) => match (optimize(*lhs), optimize(*rhs)) {
+            (Lir::Int(lhs), Lir::Int(rhs)) => bool_constant(lhs < rhs),
+            (lhs, rhs) => Lir::Lt(Box::new(lhs), Box::new(rhs)),
+        },
+        Lir::Gt(lhs, rhs) => match (optimize(*lhs), optimize(*rhs)) {
+            (Lir::Int(lhs), Lir::Int(rhs)) => bool_constant(lhs > rhs),
+            (lhs, rhs) => Lir::Gt(Box::new(lhs), Box::new(rhs)),
+        },
+        Lir::GtBytes(lhs, rhs) => match (optimize(*lhs), optimize(*rhs)) {
+            (Lir::String(lhs), Lir::String(rhs)) => bool_constant(lhs.as_bytes() > rhs.as_bytes()),
+            (lhs, rhs) => Lir::GtBytes(Box::new(lhs), Box::new(rhs)),
+        },
+        Lir::Cons(first, rest) => Lir::Cons(optimize_box(first), optimize_box(rest)),
+        Lir::Environment { value, environment } => Lir::Environment {
+            value: optimize_box(value),
+            environment: optimize_box(environment),
+        },
+        Lir::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let condition = optimize(*condition);
+            match constant_truthiness(&condition) {
+                Some(true) => optimize(*then_branch),
+                Some(false) => optimize(*else_branch),
+                None => Lir::If {
+                    condition: Box::new(condition),
+                    then_branch: optimize_box(then_branch),
+                    else_branch: optimize_box(else_branch),
+                },
+            }
+        }
+        Lir::Quote(value) => Lir::Quote(optimize_box(value)),
+        // Constants can't fail at runtime, so there's no need to keep track of their source.
+        Lir::Span(range, value) => match optimize(*value) {
+            value @ (Lir::Int(_) | Lir::String(_)) => value,
+            value => Lir::Span(range, Box::new(value)),
+        },
+    }
+}
+
+fn optimize_add(args: Vec<Lir>) -> Lir {
+    let mut constant = BigInt::zero();
+    let mut operands = Vec::new();
+
+    for arg in flatten(args, |lir| {
+        match unspan_if(lir, |lir| matches!(lir, Lir::Add(_))) {
+            Lir::Add(args) => Ok(args),
+            lir => Err(lir),
+        }
+    }) {
+        match arg {
+            Lir::Int(value) => constant += value,
+            arg => operands.push(arg),
+        }
+    }
+
+    if !constant.is_zero() || operands.is_empty() {
+        operands.push(Lir::Int(constant));
+    }
+
+    if operands.len() == 1 {
+        operands.remove(0)
+    } else {
+        Lir::Add(operands)
+    }
+}
+
+fn optimize_mul(args: Vec<Lir>) -> Lir {
+    let mut constant = BigInt::one();
+    let mut operands = Vec::new();
+
+    for arg in flatten(args, |lir| {
+        match unspan_if(lir, |lir| matches!(lir, Lir::Mul(_))) {
+            Lir::Mul(args) => Ok(args),
+            lir => Err(lir),
+        }
+    }) {
+        match arg {
+            Lir::Int(value) => constant *= value,
+            arg => operands.push(arg),
+        }
+    }
+
+    if !constant.is_one() || operands.is_empty() {
+        operands.push(Lir::Int(constant));
+    }
+
+    if operands.len() == 1 {
+        operands.remove(0)
+    } else {
+        Lir::Mul(operands)
+    }
+}
+
+fn optimize_sub(args: Vec<Lir>) -> Lir {
+    // Only the first operand can be flattened, since `(a - b) - c` is `(- a b c)`.
+    let mut args = args.into_iter();
+    let first = args
+        .next()
+        .map(|lir| unspan_if(lir, |lir| matches!(lir, Lir::Sub(_))));
+    let mut operands = match first {
+        Some(Lir::Sub(inner)) => inner,
+        Some(first) => vec![first],
+        None => return Lir::Int(BigInt::zero()),
+    };
+    operands.extend(args);
+
+    let mut operands = operands.into_iter();
+    let first = operands.next().unwrap();
+
+    let mut constant = BigInt::zero();
+    let mut subtrahends = Vec::new();
+
+    for operand in operands {
+        match operand {
+            Lir::Int(value) => constant += value,
+            operand => subtrahends.push(operand),
+        }
+    }
+
+    match first {
+        Lir::Int(value) if subtrahends.is_empty() => Lir::Int(value - constant),
+        first => {
+            if !constant.is_zero() {
+                subtrahends.push(Lir::Int(constant));
+            }
+
+            if subtrahends.is_empty() {
+                first
+            } else {
+                let mut operands = vec![first];
+                operands.extend(subtrahends);
+                Lir::Sub(operands)
+            }
+        }
+    }
+}
+
+fn optimize_div(args: Vec<Lir>) -> Lir {
+    // Division is only folded for non-negative operands, so that rounding and
+    // division by zero are left to the runtime.
+    if let [Lir::Int(lhs), Lir::Int(rhs)] = args.as_slice() {
+        if !lhs.is_negative() && rhs.is_positive() {
+            return Lir::Int(lhs / rhs);
+        }
+    }
+
+    if let [lhs, Lir::Int(rhs)] = args.as_slice() {
+        if rhs.is_one() {
+            return lhs.clone();
+        }
+    }
+
+    Lir::Div(args)
+}
+
+fn bool_constant(value: bool) -> Lir {
+    Lir::Int(BigInt::from(value as u8))
+}
+
+fn constant_truthiness(lir: &Lir) -> Option<bool> {
+    match lir {
+        Lir::Int(value) => Some(!value.is_zero()),
+        Lir::String(value) => Some(!value.is_empty()),
+        _ => None,
+    }
+}
+
+/// Removes the source ranges around a value if it matches the predicate, so that nested
+/// operators can be flattened into their parent.
+fn unspan_if(lir: Lir, predicate: fn(&Lir) -> bool) -> Lir {
+    match lir {
+        Lir::Span(range, value) => {
+            if predicate(value.unspanned()) {
+                unspan_if(*value, predicate)
+            } else {
+                Lir::Span(range, value)
+            }
+        }
+        lir => lir,
+    }
+}
+
+fn flatten(args: Vec<Lir>, unwrap: fn(Lir) -> Result<Vec<Lir>, Lir>) -> Vec<Lir> {
+    let mut result = Vec::new();
+    for arg in args {
+        match unwrap(arg) {
+            Ok(inner) => result.extend(flatten(inner, unwrap)),
+            Err(arg) => result.push(arg),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(value: i64) -> Lir {
+        Lir::Int(value.into())
+    }
+
+    fn path(path: usize) -> Lir {
+        Lir::Path(path)
+    }
+
+    #[test]
+    fn fold_arithmetic() {
+        assert_eq!(optimize(Lir::Add(vec![int(2), int(3)])), int(5));
+        assert_eq!(optimize(Lir::Sub(vec![int(2), int(3)])), int(-1));
+        assert_eq!(optimize(Lir::Mul(vec![int(2), int(3)])), int(6));
+        assert_eq!(optimize(Lir::Div(vec![int(7), int(2)])), int(3));
+        assert_eq!(
+            optimize(Lir::Mul(vec![Lir::Add(vec![int(1), int(2)]), int(4)])),
+            int(12)
+        );
+    }
+
+    #[test]
+    fn keep_runtime_division() {
+        let division_by_zero = Lir::Div(vec![int(1), int(0)]);
+        assert_eq!(optimize(division_by_zero.clone()), division_by_zero);
+
+        let negative = Lir::Div(vec![int(-7), int(2)]);
+        assert_eq!(optimize(negative.clone()), negative);
+
+        assert_eq!(optimize(Lir::Div(vec![path(2), int(1)])), path(2));
+    }
+
+    #[test]
+    fn fold_comparisons() {
+        assert_eq!(
+            optimize(Lir::Lt(Box::new(int(1)), Box::new(int(2)))),
+            int(1)
+        );
+        assert_eq!(
+            optimize(Lir::Gt(Box::new(int(1)), Box::new(int(2)))),
+            int(0)
+        );
+
+        assert_eq!(
+            optimize(Lir::GtBytes(
+                Box::new(Lir::String("b".to_string())),
+                Box::new(Lir::String("abc".to_string()))
+            )),
+            int(1)
+        );
+
+        let runtime = Lir::Gt(Box::new(path(2)), Box::new(int(2)));
+        assert_eq!(optimize(runtime.clone()), runtime);
+    }
+
+    #[test]
+    fn flatten_variadic_operators() {
+        assert_eq!(
+            optimize(Lir::Add(vec![
+                Lir::Add(vec![path(2), int(1)]),
+                Lir::Add(vec![path(5), int(2)]),
+            ])),
+            Lir::Add(vec![path(2), path(5), int(3)])
+        );
+        assert_eq!(
+            optimize(Lir::Mul(vec![Lir::Mul(vec![path(2), path(5)]), path(11)])),
+            Lir::Mul(vec![path(2), path(5), path(11)])
+        );
+        assert_eq!(
+            optimize(Lir::Sub(vec![
+                Lir::Sub(vec![path(2), int(1)]),
+                path(5),
+                int(2)
+            ])),
+            Lir::Sub(vec![path(2), path(5), int(3)])
+        );
+    }
+
+    #[test]
+    fn remove_identities() {
+        assert_eq!(optimize(Lir::Add(vec![path(2), int(0)])), path(2));
+        assert_eq!(optimize(Lir::Mul(vec![int(1), path(2)])), path(2));
+        assert_eq!(optimize(Lir::Sub(vec![path(2), int(0)])), path(2));
+    }
+
+    #[test]
+    fn constant_condition() {
+        let branch = |condition: Lir| Lir::If {
+            condition: Box::new(condition),
+            then_branch: Box::new(path(2)),
+            else_branch: Box::new(path(5)),
+        };
+
+        assert_eq!(optimize(branch(int(1))), path(2));
+        assert_eq!(optimize(branch(int(0))), path(5));
+        assert_eq!(
+            optimize(branch(Lir::Lt(Box::new(int(3)), Box::new(int(2))))),
+            path(5)
+        );
+        assert_eq!(optimize(branch(path(11))), branch(path(11)));
+    }
+
+    #[test]
+    fn optimize_quoted_bodies() {
+        assert_eq!(
+            optimize(Lir::Quote(Box::new(Lir::Add(vec![path(2), int(0)])))),
+            Lir::Quote(Box::new(path(2)))
+        );
+    }
+
+    #[test]
+    fn keep_spans_around_runtime_values() {
+        let span = |value: Lir| Lir::Span((0..5).into(), Box::new(value));
+
+        assert_eq!(optimize(span(Lir::Add(vec![int(1), int(2)]))), int(3));
+        assert_eq!(
+            optimize(Lir::Add(vec![
+                span(Lir::Add(vec![path(2), int(1)])),
+                int(2)
+            ])),
+            Lir::Add(vec![path(2), int(3)])
+        );
+
+        let division = span(Lir::Div(vec![path(2), path(5)]));
+        assert_eq!(optimize(division.clone()), division);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use crate::{optimize, Lir};
+
+/// An optimization pass which can be individually enabled or disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Pass {
+    /// Inlines functions marked `inline` and functions which are only called once.
+    Inline,
+    /// Inlines small functions at every call site, even if that duplicates their body.
+    InlineSmall,
+    /// Arranges environments as balanced trees, with the most used values at the shortest paths.
+    EnvironmentLayout,
+    /// Folds constants and simplifies arithmetic and conditions.
+    ConstantFolding,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 4] = [
+        Pass::Inline,
+        Pass::InlineSmall,
+        Pass::EnvironmentLayout,
+        Pass::ConstantFolding,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Inline => "inline",
+            Self::InlineSmall => "inline-small",
+            Self::EnvironmentLayout => "env-layout",
+            Self::ConstantFolding => "const-fold",
+        }
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Pass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|pass| pass.name() == s)
+            .ok_or_else(|| {
+                let names = Self::ALL.map(Pass::name).join(", ");
+                format!("unknown pass `{s}`, expected one of: {names}")
+            })
+    }
+}
+
+/// A preset selection of passes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptLevel {
+    /// No optimizations, which is useful for bisecting miscompilations.
+    O0,
+    /// Optimizations which never make the program larger or more expensive.
+    O1,
+    /// Every optimization, which minimizes execution cost.
+    #[default]
+    O2,
+    /// Optimizations which minimize the size of the program.
+    Os,
+}
+
+impl OptLevel {
+    pub fn passes(self) -> &'static [Pass] {
+        match self {
+            Self::O0 => &[],
+            Self::O1 => &[Pass::EnvironmentLayout, Pass::ConstantFolding],
+            Self::O2 => &Pass::ALL,
+            Self::Os => &[Pass::Inline, Pass::EnvironmentLayout, Pass::ConstantFolding],
+        }
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::O0 => write!(f, "0"),
+            Self::O1 => write!(f, "1"),
+            Self::O2 => write!(f, "2"),
+            Self::Os => write!(f, "s"),
+        }
+    }
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(Self::O0),
+            "1" => Ok(Self::O1),
+            "2" => Ok(Self::O2),
+            "s" => Ok(Self::Os),
+            _ => Err(format!(
+                "unknown optimization level `{s}`, expected one of: 0, 1, 2, s"
+            )),
+        }
+    }
+}
+
+/// Keeps track of which passes are enabled, and runs those that operate on `Lir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassManager {
+    enabled: BTreeSet<Pass>,
+}
+
+impl PassManager {
+    pub fn new(level: OptLevel) -> Self {
+        Self {
+            enabled: level.passes().iter().copied().collect(),
+        }
+    }
+
+    pub fn enable(&mut self, pass: Pass) {
+        self.enabled.insert(pass);
+    }
+
+    pub fn disable(&mut self, pass: Pass) {
+        self.enabled.remove(&pass);
+    }
+
+    pub fn is_enabled(&self, pass: Pass) -> bool {
+        self.enabled.contains(&pass)
+    }
+
+    pub fn enabled_passes(&self) -> impl Iterator<Item = Pass> + '_ {
+        self.enabled.iter().copied()
+    }
+
+    /// Runs the enabled passes which transform an already lowered program.
+    pub fn run(&self, lir: Lir) -> Lir {
+        if self.is_enabled(Pass::ConstantFolding) {
+            optimize(lir)
+        } else {
+            lir
+        }
+    }
+}
+
+impl Default for PassManager {
+    fn default() -> Self {
+        Self::new(OptLevel::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_passes() {
+        for pass in Pass::ALL {
+            assert_eq!(pass.name().parse(), Ok(pass));
+        }
+        assert!("unknown".parse::<Pass>().is_err());
+    }
+
+    #[test]
+    fn parse_levels() {
+        for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2, OptLevel::Os] {
+            assert_eq!(level.to_string().parse(), Ok(level));
+        }
+        assert!("3".parse::<OptLevel>().is_err());
+    }
+
+    #[test]
+    fn toggle_passes() {
+        let mut passes = PassManager::new(OptLevel::O0);
+        assert_eq!(passes.enabled_passes().count(), 0);
+
+        passes.enable(Pass::Inline);
+        assert!(passes.is_enabled(Pass::Inline));
+
+        let mut passes = PassManager::new(OptLevel::O2);
+        passes.disable(Pass::Inline);
+        assert!(!passes.is_enabled(Pass::Inline));
+        assert!(passes.is_enabled(Pass::InlineSmall));
+    }
+
+    #[test]
+    fn run_constant_folding() {
+        let lir = Lir::Add(vec![Lir::Int(1.into()), Lir::Int(2.into())]);
+        assert_eq!(PassManager::new(OptLevel::O0).run(lir.clone()), lir);
+        assert_eq!(PassManager::new(OptLevel::O1).run(lir), Lir::Int(3.into()));
+    }
+}
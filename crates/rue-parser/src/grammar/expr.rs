@@ -1,4 +1,5 @@
 use rowan::Checkpoint;
+use rue_error::Code;
 use rue_syntax::{SyntaxKind, T};
 
 use crate::{grammar::parse_block, parser::Parser};
@@ -20,8 +21,10 @@ fn parse_binary_expr(p: &mut Parser, min_binding_power: u8) {
         parse_prefix_expr(checkpoint, p, 7);
     } else if p.at(T!['(']) {
         parse_group_expr(p);
+    } else if p.at(T!['[']) {
+        parse_list_expr(p);
     } else {
-        return p.error("expected expression".to_string());
+        return p.error(Code::ExpectedExpression, "expected expression".to_string());
     }
 
     if p.at(T!['(']) {
@@ -64,6 +67,24 @@ fn parse_group_expr(p: &mut Parser) {
     p.expect(T![')']);
 }
 
+fn parse_list_expr(p: &mut Parser) {
+    p.start(SyntaxKind::ListExpr);
+    p.bump();
+
+    while !p.at_set(&[T![']'], SyntaxKind::Eof]) {
+        parse_expr(p);
+
+        if p.at(T![,]) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
+
+    p.expect(T![']']);
+    p.finish();
+}
+
 fn parse_call_expr(checkpoint: Checkpoint, p: &mut Parser) {
     p.start_at(checkpoint, SyntaxKind::CallExpr);
     p.bump();
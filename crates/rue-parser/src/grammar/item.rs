@@ -1,3 +1,4 @@
+use rue_error::Code;
 use rue_syntax::{SyntaxKind, T};
 
 use crate::parser::Parser;
@@ -5,15 +6,21 @@ use crate::parser::Parser;
 use super::{parse_block, ty::parse_type};
 
 pub(super) fn parse_item(p: &mut Parser) {
-    if p.at(T![fn]) {
+    if p.at_set(&[T![#], T![inline], T![fn]]) {
         parse_fn_item(p);
     } else {
-        p.error("expected item".to_string());
+        p.error(Code::ExpectedItem, "expected item".to_string());
     }
 }
 
 fn parse_fn_item(p: &mut Parser) {
     p.start(SyntaxKind::FnItem);
+    while p.at(T![#]) {
+        parse_attribute(p);
+    }
+    if p.at(T![inline]) {
+        p.bump();
+    }
     p.expect(T![fn]);
     p.expect(SyntaxKind::Ident);
     parse_fn_param_list(p);
@@ -23,6 +30,15 @@ fn parse_fn_item(p: &mut Parser) {
     p.finish();
 }
 
+fn parse_attribute(p: &mut Parser) {
+    p.start(SyntaxKind::Attribute);
+    p.expect(T![#]);
+    p.expect(T!['[']);
+    p.expect(SyntaxKind::Ident);
+    p.expect(T![']']);
+    p.finish();
+}
+
 fn parse_fn_param_list(p: &mut Parser) {
     p.start(SyntaxKind::FnParamList);
     p.expect(T!['(']);
@@ -43,6 +59,9 @@ fn parse_fn_param_list(p: &mut Parser) {
 
 fn parse_fn_param(p: &mut Parser) {
     p.start(SyntaxKind::FnParam);
+    if p.at(T![curry]) {
+        p.bump();
+    }
     p.expect(SyntaxKind::Ident);
     p.expect(T![:]);
     parse_type(p);
@@ -81,22 +100,193 @@ mod tests {
                       Whitespace@32..33 " "
                       OpenBrace@33..34 "{"
                       Whitespace@34..35 " "
-                      Integer@35..37 "42"
-                      Whitespace@37..38 " "
+                      LiteralExpr@35..38
+                        Integer@35..37 "42"
+                        Whitespace@37..38 " "
                       CloseBrace@38..39 "}""#]],
         );
     }
 
+    #[test]
+    fn parse_inline_fn() {
+        check_program(
+            "inline fn one() -> Int { 1 }",
+            expect![[r#"
+                Program@0..28
+                  FnItem@0..28
+                    Inline@0..6 "inline"
+                    Whitespace@6..7 " "
+                    Fn@7..9 "fn"
+                    Whitespace@9..10 " "
+                    Ident@10..13 "one"
+                    FnParamList@13..16
+                      OpenParen@13..14 "("
+                      CloseParen@14..15 ")"
+                      Whitespace@15..16 " "
+                    Arrow@16..18 "->"
+                    Whitespace@18..19 " "
+                    Ident@19..22 "Int"
+                    Block@22..28
+                      Whitespace@22..23 " "
+                      OpenBrace@23..24 "{"
+                      Whitespace@24..25 " "
+                      LiteralExpr@25..27
+                        Integer@25..26 "1"
+                        Whitespace@26..27 " "
+                      CloseBrace@27..28 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_attributes() {
+        check_program(
+            "#[test] #[should_fail] fn f() -> Int { 1 }",
+            expect![[r##"
+                Program@0..42
+                  FnItem@0..42
+                    Attribute@0..8
+                      Pound@0..1 "#"
+                      OpenBracket@1..2 "["
+                      Ident@2..6 "test"
+                      CloseBracket@6..7 "]"
+                      Whitespace@7..8 " "
+                    Attribute@8..23
+                      Pound@8..9 "#"
+                      OpenBracket@9..10 "["
+                      Ident@10..21 "should_fail"
+                      CloseBracket@21..22 "]"
+                      Whitespace@22..23 " "
+                    Fn@23..25 "fn"
+                    Whitespace@25..26 " "
+                    Ident@26..27 "f"
+                    FnParamList@27..30
+                      OpenParen@27..28 "("
+                      CloseParen@28..29 ")"
+                      Whitespace@29..30 " "
+                    Arrow@30..32 "->"
+                    Whitespace@32..33 " "
+                    Ident@33..36 "Int"
+                    Block@36..42
+                      Whitespace@36..37 " "
+                      OpenBrace@37..38 "{"
+                      Whitespace@38..39 " "
+                      LiteralExpr@39..41
+                        Integer@39..40 "1"
+                        Whitespace@40..41 " "
+                      CloseBrace@41..42 "}""##]],
+        );
+    }
+
+    #[test]
+    fn parse_curried_param() {
+        check_program(
+            "fn main(curry a: Int, b: Int) -> Int { a }",
+            expect![[r#"
+                Program@0..42
+                  FnItem@0..42
+                    Fn@0..2 "fn"
+                    Whitespace@2..3 " "
+                    Ident@3..7 "main"
+                    FnParamList@7..30
+                      OpenParen@7..8 "("
+                      FnParam@8..20
+                        Curry@8..13 "curry"
+                        Whitespace@13..14 " "
+                        Ident@14..15 "a"
+                        Colon@15..16 ":"
+                        Whitespace@16..17 " "
+                        Ident@17..20 "Int"
+                      Comma@20..21 ","
+                      Whitespace@21..22 " "
+                      FnParam@22..28
+                        Ident@22..23 "b"
+                        Colon@23..24 ":"
+                        Whitespace@24..25 " "
+                        Ident@25..28 "Int"
+                      CloseParen@28..29 ")"
+                      Whitespace@29..30 " "
+                    Arrow@30..32 "->"
+                    Whitespace@32..33 " "
+                    Ident@33..36 "Int"
+                    Block@36..42
+                      Whitespace@36..37 " "
+                      OpenBrace@37..38 "{"
+                      Whitespace@38..39 " "
+                      LiteralExpr@39..41
+                        Ident@39..40 "a"
+                        Whitespace@40..41 " "
+                      CloseBrace@41..42 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_list_type_and_expr() {
+        check_program(
+            "fn f(a: [Int]) -> [[Int]] { [a, []] }",
+            expect![[r#"
+                Program@0..37
+                  FnItem@0..37
+                    Fn@0..2 "fn"
+                    Whitespace@2..3 " "
+                    Ident@3..4 "f"
+                    FnParamList@4..15
+                      OpenParen@4..5 "("
+                      FnParam@5..13
+                        Ident@5..6 "a"
+                        Colon@6..7 ":"
+                        Whitespace@7..8 " "
+                        ListType@8..13
+                          OpenBracket@8..9 "["
+                          Ident@9..12 "Int"
+                          CloseBracket@12..13 "]"
+                      CloseParen@13..14 ")"
+                      Whitespace@14..15 " "
+                    Arrow@15..17 "->"
+                    Whitespace@17..18 " "
+                    ListType@18..26
+                      OpenBracket@18..19 "["
+                      ListType@19..24
+                        OpenBracket@19..20 "["
+                        Ident@20..23 "Int"
+                        CloseBracket@23..24 "]"
+                      CloseBracket@24..25 "]"
+                      Whitespace@25..26 " "
+                    Block@26..37
+                      OpenBrace@26..27 "{"
+                      Whitespace@27..28 " "
+                      ListExpr@28..36
+                        OpenBracket@28..29 "["
+                        LiteralExpr@29..30
+                          Ident@29..30 "a"
+                        Comma@30..31 ","
+                        Whitespace@31..32 " "
+                        ListExpr@32..34
+                          OpenBracket@32..33 "["
+                          CloseBracket@33..34 "]"
+                        CloseBracket@34..35 "]"
+                        Whitespace@35..36 " "
+                      CloseBrace@36..37 "}""#]],
+        );
+    }
+
     #[test]
     fn parse_incomplete_fn_keyword() {
         check_program(
             "fn",
             expect![[r#"
-            Program@0..2
-              FnItem@0..2
-                Fn@0..2 "fn"
-                FnParamList@2..2
-                Block@2..2"#]],
+                Program@0..2
+                  FnItem@0..2
+                    Fn@0..2 "fn"
+                    Error@2..2
+                    FnParamList@2..2
+                      Error@2..2
+                      Error@2..2
+                    Error@2..2
+                    Error@2..2
+                    Block@2..2
+                      Error@2..2
+                      Error@2..2
+                      Error@2..2"#]],
         );
     }
 
@@ -111,7 +301,14 @@ mod tests {
                     Whitespace@2..3 " "
                     Ident@3..13 "incomplete"
                     FnParamList@13..13
-                    Block@13..13"#]],
+                      Error@13..13
+                      Error@13..13
+                    Error@13..13
+                    Error@13..13
+                    Block@13..13
+                      Error@13..13
+                      Error@13..13
+                      Error@13..13"#]],
         );
     }
 }
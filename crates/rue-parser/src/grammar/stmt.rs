@@ -1,3 +1,4 @@
+use rue_error::Code;
 use rue_syntax::{SyntaxKind, T};
 
 use crate::parser::Parser;
@@ -10,7 +11,7 @@ pub(super) fn parse_stmt(p: &mut Parser) {
     if p.at(T![let]) {
         parse_let_stmt(p);
     } else {
-        p.error("expected statement".to_string());
+        p.error(Code::ExpectedStatement, "expected statement".to_string());
     }
 }
 
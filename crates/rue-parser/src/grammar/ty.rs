@@ -1,11 +1,18 @@
-use rue_syntax::SyntaxKind;
+use rue_error::Code;
+use rue_syntax::{SyntaxKind, T};
 
 use crate::parser::Parser;
 
 pub(super) fn parse_type(p: &mut Parser) {
     if p.at(SyntaxKind::Ident) {
         p.bump();
+    } else if p.at(T!['[']) {
+        p.start(SyntaxKind::ListType);
+        p.bump();
+        parse_type(p);
+        p.expect(T![']']);
+        p.finish();
     } else {
-        p.error("expected type".to_string());
+        p.error(Code::ExpectedType, "expected type".to_string());
     }
 }
@@ -1,15 +1,40 @@
 use grammar::parse_program;
 use parser::Parser;
-use rue_error::Error;
+use rue_error::Diagnostic;
 use rue_lexer::{Lexer, Token};
 use rue_syntax::SyntaxNode;
 
 mod grammar;
 mod parser;
 
-pub fn parse_text(source: &str) -> (Vec<Error>, SyntaxNode) {
+pub fn parse_text(source: &str) -> (Vec<Diagnostic>, SyntaxNode) {
     let tokens: Vec<Token> = Lexer::new(source).collect();
     let mut parser = Parser::new(&tokens);
     parse_program(&mut parser);
     parser.output()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(source: &str) -> Vec<String> {
+        parse_text(source)
+            .0
+            .into_iter()
+            .map(|error| error.message().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn expected_tokens() {
+        assert_eq!(
+            messages("fn main() -> Int { 1"),
+            vec!["found eof, expected one of: `(`, `<`, `>`, `+`, `-`, `*`, `/`, `}`"]
+        );
+        assert_eq!(
+            messages("fn main() Int { 1 }")[0],
+            "found identifier, expected one of: `->`"
+        );
+    }
+}
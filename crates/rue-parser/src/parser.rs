@@ -3,16 +3,16 @@ use std::mem;
 use indexmap::IndexSet;
 use itertools::Itertools;
 use rowan::{Checkpoint, GreenNodeBuilder, Language};
-use rue_error::Error;
+use rue_error::{Code, Diagnostic};
 use rue_lexer::Token;
 use rue_syntax::{RueLang, SyntaxKind, SyntaxNode, T};
 
 #[allow(unused)]
-const RECOVERY_SET: [SyntaxKind; 5] = [T!['{'], T!['}'], T![;], T![fn], T![let]];
+const RECOVERY_SET: [SyntaxKind; 6] = [T!['{'], T!['}'], T![;], T![inline], T![fn], T![let]];
 
 pub(crate) struct Parser<'a> {
     tokens: Vec<(SyntaxKind, &'a str)>,
-    errors: Vec<Error>,
+    errors: Vec<Diagnostic>,
     builder: GreenNodeBuilder<'static>,
     pos: usize,
     text_pos: usize,
@@ -24,7 +24,7 @@ impl<'a> Parser<'a> {
         let mut errors = Vec::new();
         let mut pos = 0;
         let tokens = input
-            .into_iter()
+            .iter()
             .map(|token| {
                 let result = convert_token(token, pos, &mut errors);
                 pos += token.text.len();
@@ -42,7 +42,7 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub(crate) fn output(self) -> (Vec<Error>, SyntaxNode) {
+    pub(crate) fn output(self) -> (Vec<Diagnostic>, SyntaxNode) {
         let green_node = self.builder.finish();
         (self.errors, SyntaxNode::new_root(green_node))
     }
@@ -69,16 +69,17 @@ impl<'a> Parser<'a> {
     }
 
     pub(crate) fn expect(&mut self, kind: SyntaxKind) {
-        self.expected_kinds.clear();
         self.eat_trivia();
         if let Some(num_tokens) = self.peek_tokens_of(kind) {
+            self.expected_kinds.clear();
             self.add_tokens(kind, num_tokens);
         } else {
+            self.expected_kinds.insert(kind);
             self.unexpected_token_error();
         }
     }
 
-    pub(crate) fn error(&mut self, message: String) {
+    pub(crate) fn error(&mut self, code: Code, message: String) {
         self.eat_trivia();
 
         let eof = (SyntaxKind::Eof, self.text_pos..self.text_pos);
@@ -88,21 +89,22 @@ impl<'a> Parser<'a> {
             (token.0, range)
         });
 
-        self.errors.push(Error::new(message, range.into()));
+        self.errors
+            .push(Diagnostic::error(code, message, range.into()));
         self.add_error_token();
     }
 
     pub(crate) fn expected(&mut self) -> String {
-        mem::take(&mut self.expected_kinds)
-            .into_iter()
-            .map(|kind| format!("`{kind}`"))
-            .join(", ")
+        mem::take(&mut self.expected_kinds).into_iter().join(", ")
     }
 
     pub(crate) fn unexpected_token_error(&mut self) {
         let found = self.peek();
         let expected = self.expected();
-        self.error(format!("found {found}, expected one of: {expected}",));
+        self.error(
+            Code::UnexpectedToken,
+            format!("found {found}, expected one of: {expected}"),
+        );
 
         // if !self.at_set(&RECOVERY_SET) && !self.at_eof() {
         //     self.add_error_token();
@@ -181,25 +183,31 @@ impl<'a> Parser<'a> {
 fn convert_token<'a>(
     token: &'a Token,
     pos: usize,
-    errors: &mut Vec<Error>,
+    errors: &mut Vec<Diagnostic>,
 ) -> (SyntaxKind, &'a str) {
     use rue_lexer::TokenKind as T;
 
-    let mut error = |message: String| {
+    let mut error = |code: Code, message: String| {
         let range = pos..(pos + token.text.len());
-        errors.push(Error::new(message, range.into()));
+        errors.push(Diagnostic::error(code, message, range.into()));
     };
 
     let kind = match token.kind {
         T::Unknown => {
-            error(format!("unknown token `{}`", token.text));
+            error(
+                Code::UnknownToken,
+                format!("unknown token `{}`", token.text),
+            );
             SyntaxKind::Unknown
         }
         T::Whitespace => SyntaxKind::Whitespace,
         T::LineComment => SyntaxKind::LineComment,
         T::BlockComment { is_terminated } => {
             if !is_terminated {
-                error(format!("unterminated block comment"));
+                error(
+                    Code::UnterminatedBlockComment,
+                    "unterminated block comment".to_string(),
+                );
             }
             SyntaxKind::BlockComment
         }
@@ -208,12 +216,17 @@ fn convert_token<'a>(
         T::Integer => SyntaxKind::Integer,
         T::String { is_terminated } => {
             if !is_terminated {
-                error(format!("unterminated string literal"));
+                error(
+                    Code::UnterminatedString,
+                    "unterminated string literal".to_string(),
+                );
             }
             SyntaxKind::String
         }
 
         T::Fn => T![fn],
+        T::Inline => T![inline],
+        T::Curry => T![curry],
         T::If => T![if],
         T::Else => T![else],
         T::Let => T![let],
@@ -234,6 +247,7 @@ fn convert_token<'a>(
         T::OpenBrace => T!['{'],
         T::CloseBrace => T!['}'],
 
+        T::Pound => T![#],
         T::Dot => T![.],
         T::Comma => T![,],
         T::Colon => T![:],
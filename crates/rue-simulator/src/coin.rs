@@ -0,0 +1,85 @@
+use clvmr::sha2::{Digest, Sha256};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use rue_compiler::TreeHash;
+
+/// A 32 byte hash, such as a coin id or an announcement id.
+pub type Bytes32 = [u8; 32];
+
+/// A coin is identified by its parent, the hash of the puzzle that locks it, and its amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Coin {
+    pub parent_coin_info: Bytes32,
+    pub puzzle_hash: TreeHash,
+    pub amount: u64,
+}
+
+impl Coin {
+    pub fn new(parent_coin_info: Bytes32, puzzle_hash: TreeHash, amount: u64) -> Self {
+        Self {
+            parent_coin_info,
+            puzzle_hash,
+            amount,
+        }
+    }
+
+    /// The id of the coin, which is the hash of its parent, puzzle hash and amount.
+    /// The amount is hashed as a CLVM integer, so it has no leading zeros.
+    pub fn coin_id(&self) -> Bytes32 {
+        let mut hasher = Sha256::new();
+        hasher.update(self.parent_coin_info);
+        hasher.update(self.puzzle_hash);
+        hasher.update(int_bytes(self.amount));
+        hasher.finalize().into()
+    }
+}
+
+/// A spend of a coin, with the puzzle it is locked by and the solution to run it with.
+/// Both are serialized CLVM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoinSpend {
+    pub coin: Coin,
+    pub puzzle_reveal: Vec<u8>,
+    pub solution: Vec<u8>,
+}
+
+impl CoinSpend {
+    pub fn new(coin: Coin, puzzle_reveal: Vec<u8>, solution: Vec<u8>) -> Self {
+        Self {
+            coin,
+            puzzle_reveal,
+            solution,
+        }
+    }
+}
+
+fn int_bytes(value: u64) -> Vec<u8> {
+    let value = BigInt::from(value);
+    if value.is_zero() {
+        Vec::new()
+    } else {
+        value.to_signed_bytes_be()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coin_ids() {
+        let coin = Coin::new([1; 32], [2; 32], 0);
+        let mut hasher = Sha256::new();
+        hasher.update([1; 32]);
+        hasher.update([2; 32]);
+        assert_eq!(coin.coin_id(), <[u8; 32]>::from(hasher.finalize()));
+
+        // An amount with the high bit set needs a leading zero to stay positive.
+        let coin = Coin::new([1; 32], [2; 32], 128);
+        let mut hasher = Sha256::new();
+        hasher.update([1; 32]);
+        hasher.update([2; 32]);
+        hasher.update([0, 128]);
+        assert_eq!(coin.coin_id(), <[u8; 32]>::from(hasher.finalize()));
+    }
+}
@@ -0,0 +1,206 @@
+use clvmr::{
+    allocator::{NodePtr, SExp},
+    Allocator,
+};
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+
+use crate::{Bytes32, SimulatorError};
+
+/// A condition output by a puzzle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    AggSigUnsafe {
+        public_key: Vec<u8>,
+        message: Vec<u8>,
+    },
+    AggSigMe {
+        public_key: Vec<u8>,
+        message: Vec<u8>,
+    },
+    CreateCoin {
+        puzzle_hash: Bytes32,
+        amount: u64,
+    },
+    ReserveFee(u64),
+    CreateCoinAnnouncement(Vec<u8>),
+    AssertCoinAnnouncement(Bytes32),
+    CreatePuzzleAnnouncement(Vec<u8>),
+    AssertPuzzleAnnouncement(Bytes32),
+    AssertMyCoinId(Bytes32),
+    AssertMyParentId(Bytes32),
+    AssertMyPuzzleHash(Bytes32),
+    AssertMyAmount(u64),
+    AssertSecondsRelative(u64),
+    AssertSecondsAbsolute(u64),
+    AssertHeightRelative(u64),
+    AssertHeightAbsolute(u64),
+}
+
+/// Parses the list of conditions output by a puzzle.
+///
+/// Conditions with an unknown opcode are ignored, as they are on chain, and any
+/// arguments after the ones a condition needs are ignored as well.
+pub fn parse_conditions(
+    allocator: &Allocator,
+    output: NodePtr,
+) -> Result<Vec<Condition>, SimulatorError> {
+    list(allocator, output, "the output of the puzzle")?
+        .into_iter()
+        .filter_map(|condition| parse_condition(allocator, condition).transpose())
+        .collect()
+}
+
+fn parse_condition(
+    allocator: &Allocator,
+    condition: NodePtr,
+) -> Result<Option<Condition>, SimulatorError> {
+    let items = list(allocator, condition, "a condition")?;
+    let Some((&opcode, args)) = items.split_first() else {
+        return Err(invalid("a condition can't be empty"));
+    };
+
+    let Some(opcode) = int(allocator, opcode).to_u8() else {
+        return Ok(None);
+    };
+
+    let arity = match opcode {
+        49..=51 => 2,
+        52 | 60..=63 | 70..=73 | 80..=83 => 1,
+        _ => return Ok(None),
+    };
+
+    if args.len() < arity {
+        return Err(invalid(&format!(
+            "condition {opcode} expects {arity} arguments, but was given {}",
+            args.len()
+        )));
+    }
+
+    let bytes = |index: usize| atom(allocator, args[index]).map(<[u8]>::to_vec);
+    let hash = |index: usize| bytes32(allocator, args[index]);
+    let amount = |index: usize| u64_value(allocator, args[index]);
+
+    Ok(Some(match opcode {
+        49 => Condition::AggSigUnsafe {
+            public_key: bytes(0)?,
+            message: bytes(1)?,
+        },
+        50 => Condition::AggSigMe {
+            public_key: bytes(0)?,
+            message: bytes(1)?,
+        },
+        51 => Condition::CreateCoin {
+            puzzle_hash: hash(0)?,
+            amount: amount(1)?,
+        },
+        52 => Condition::ReserveFee(amount(0)?),
+        60 => Condition::CreateCoinAnnouncement(bytes(0)?),
+        61 => Condition::AssertCoinAnnouncement(hash(0)?),
+        62 => Condition::CreatePuzzleAnnouncement(bytes(0)?),
+        63 => Condition::AssertPuzzleAnnouncement(hash(0)?),
+        70 => Condition::AssertMyCoinId(hash(0)?),
+        71 => Condition::AssertMyParentId(hash(0)?),
+        72 => Condition::AssertMyPuzzleHash(hash(0)?),
+        73 => Condition::AssertMyAmount(amount(0)?),
+        80 => Condition::AssertSecondsRelative(amount(0)?),
+        81 => Condition::AssertSecondsAbsolute(amount(0)?),
+        82 => Condition::AssertHeightRelative(amount(0)?),
+        83 => Condition::AssertHeightAbsolute(amount(0)?),
+        _ => unreachable!(),
+    }))
+}
+
+fn list(
+    allocator: &Allocator,
+    mut node: NodePtr,
+    what: &str,
+) -> Result<Vec<NodePtr>, SimulatorError> {
+    let mut items = Vec::new();
+    while let SExp::Pair(first, rest) = allocator.sexp(node) {
+        items.push(first);
+        node = rest;
+    }
+    if !allocator.atom(node).is_empty() {
+        return Err(invalid(&format!("{what} must be a nil terminated list")));
+    }
+    Ok(items)
+}
+
+fn atom(allocator: &Allocator, node: NodePtr) -> Result<&[u8], SimulatorError> {
+    match allocator.sexp(node) {
+        SExp::Atom => Ok(allocator.atom(node)),
+        SExp::Pair(..) => Err(invalid("expected an atom, but found a pair")),
+    }
+}
+
+fn int(allocator: &Allocator, node: NodePtr) -> BigInt {
+    match allocator.sexp(node) {
+        SExp::Atom => allocator.number(node),
+        SExp::Pair(..) => BigInt::from(-1),
+    }
+}
+
+fn bytes32(allocator: &Allocator, node: NodePtr) -> Result<Bytes32, SimulatorError> {
+    let bytes = atom(allocator, node)?;
+    bytes
+        .try_into()
+        .map_err(|_| invalid(&format!("expected 32 bytes, but found {}", bytes.len())))
+}
+
+fn u64_value(allocator: &Allocator, node: NodePtr) -> Result<u64, SimulatorError> {
+    atom(allocator, node)?;
+    let value = allocator.number(node);
+    match (value.sign(), value.to_u64()) {
+        (Sign::Minus, _) => Err(invalid(&format!(
+            "expected a positive amount, but found {value}"
+        ))),
+        (_, Some(value)) => Ok(value),
+        (_, None) => Err(invalid(&format!(
+            "the amount {value} doesn't fit in 64 bits"
+        ))),
+    }
+}
+
+fn invalid(message: &str) -> SimulatorError {
+    SimulatorError::InvalidCondition(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use rue_compiler::assemble;
+
+    use super::*;
+
+    fn check(output: &str) -> Result<Vec<Condition>, SimulatorError> {
+        let mut allocator = Allocator::new();
+        let output = assemble(&mut allocator, output).unwrap();
+        parse_conditions(&allocator, output)
+    }
+
+    #[test]
+    fn parse_known_conditions() {
+        let hash = format!("0x{}", "ab".repeat(32));
+        assert_eq!(
+            check(&format!(
+                "((51 {hash} 100) (52 5) (73 100 \"extra\") (1 2 3))"
+            )),
+            Ok(vec![
+                Condition::CreateCoin {
+                    puzzle_hash: [0xab; 32],
+                    amount: 100
+                },
+                Condition::ReserveFee(5),
+                Condition::AssertMyAmount(100),
+            ])
+        );
+    }
+
+    #[test]
+    fn reject_invalid_conditions() {
+        assert!(check("((51 0x00 100))").is_err());
+        assert!(check("((52))").is_err());
+        assert!(check("((52 -1))").is_err());
+        assert!(check("((52 5) . 1)").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use std::{error, fmt};
+
+use rue_compiler::RunError;
+
+use crate::{Bytes32, Condition};
+
+/// The reason a spend bundle was rejected by the simulator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulatorError {
+    /// The coin was never created.
+    UnknownCoin(Bytes32),
+    /// The coin was spent by an earlier spend, or twice in the same bundle.
+    DoubleSpend(Bytes32),
+    /// The revealed puzzle doesn't hash to the puzzle hash of the coin.
+    WrongPuzzle(Bytes32),
+    /// The puzzle or solution isn't valid serialized CLVM.
+    Deserialize(String),
+    /// The puzzle failed when it was run.
+    Run(RunError),
+    /// The puzzle output something that isn't a valid list of conditions.
+    InvalidCondition(String),
+    /// A condition that asserts something about the spend wasn't true.
+    FailedAssertion {
+        coin_id: Bytes32,
+        condition: Condition,
+    },
+    /// A coin with the same id would be created twice, or already exists.
+    DuplicateCoin(Bytes32),
+    /// The coins created are worth more than the coins spent.
+    AmountNotConserved { spent: u128, created: u128 },
+    /// The fees left over are less than the reserved fee.
+    InsufficientFee { fee: u128, reserved: u128 },
+}
+
+impl fmt::Display for SimulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownCoin(coin_id) => write!(f, "coin {} doesn't exist", hex::encode(coin_id)),
+            Self::DoubleSpend(coin_id) => {
+                write!(f, "coin {} has already been spent", hex::encode(coin_id))
+            }
+            Self::WrongPuzzle(coin_id) => write!(
+                f,
+                "the puzzle doesn't match the puzzle hash of coin {}",
+                hex::encode(coin_id)
+            ),
+            Self::Deserialize(message) => write!(f, "invalid serialized value: {message}"),
+            Self::Run(error) => write!(f, "{error}"),
+            Self::InvalidCondition(message) => write!(f, "invalid condition: {message}"),
+            Self::FailedAssertion { coin_id, condition } => write!(
+                f,
+                "assertion {condition:?} failed for coin {}",
+                hex::encode(coin_id)
+            ),
+            Self::DuplicateCoin(coin_id) => {
+                write!(f, "coin {} would be created twice", hex::encode(coin_id))
+            }
+            Self::AmountNotConserved { spent, created } => write!(
+                f,
+                "created coins worth {created}, but only spent coins worth {spent}"
+            ),
+            Self::InsufficientFee { fee, reserved } => {
+                write!(f, "reserved a fee of {reserved}, but the fee is only {fee}")
+            }
+        }
+    }
+}
+
+impl error::Error for SimulatorError {}
+
+impl From<RunError> for SimulatorError {
+    fn from(error: RunError) -> Self {
+        Self::Run(error)
+    }
+}
@@ -0,0 +1,9 @@
+mod coin;
+mod condition;
+mod error;
+mod simulator;
+
+pub use coin::*;
+pub use condition::*;
+pub use error::*;
+pub use simulator::*;
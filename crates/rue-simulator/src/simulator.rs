@@ -0,0 +1,492 @@
+use std::collections::{HashMap, HashSet};
+
+use clvmr::{
+    serde::node_from_bytes,
+    sha2::{Digest, Sha256},
+    Allocator,
+};
+use rue_compiler::{run, tree_hash, TreeHash, DEFAULT_MAX_COST};
+
+use crate::{parse_conditions, Bytes32, Coin, CoinSpend, Condition, SimulatorError};
+
+/// A coin along with when it was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoinRecord {
+    pub coin: Coin,
+    pub created_height: u32,
+    pub created_timestamp: u64,
+    pub spent: bool,
+}
+
+/// What happened when a spend bundle was accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendOutcome {
+    /// The conditions output by each spend, in the same order as the spends.
+    pub conditions: Vec<Vec<Condition>>,
+    /// The coins created by the bundle.
+    pub created_coins: Vec<Coin>,
+    /// The amount spent which wasn't used to create new coins.
+    pub fee: u128,
+    /// The total cost of running the puzzles.
+    pub cost: u64,
+}
+
+/// A fake blockchain which runs spends locally, so puzzles can be tested offline.
+///
+/// Spends are checked the same way they would be on chain, except that signatures
+/// are not verified. `AGG_SIG_*` conditions are still output so tests can check them.
+#[derive(Debug, Clone, Default)]
+pub struct Simulator {
+    coins: HashMap<Bytes32, CoinRecord>,
+    height: u32,
+    timestamp: u64,
+    next_parent: u64,
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Advances the height of the chain, as if the blocks had been farmed.
+    pub fn pass_blocks(&mut self, blocks: u32) {
+        self.height += blocks;
+    }
+
+    /// Advances the timestamp of the chain.
+    pub fn pass_time(&mut self, seconds: u64) {
+        self.timestamp += seconds;
+    }
+
+    /// Creates a new unspent coin out of nothing, with a unique parent.
+    pub fn new_coin(&mut self, puzzle_hash: TreeHash, amount: u64) -> Coin {
+        let mut parent_coin_info = [0; 32];
+        parent_coin_info[24..].copy_from_slice(&self.next_parent.to_be_bytes());
+        self.next_parent += 1;
+
+        let coin = Coin::new(parent_coin_info, puzzle_hash, amount);
+        self.insert_coin(coin);
+        coin
+    }
+
+    pub fn coin_record(&self, coin_id: &Bytes32) -> Option<&CoinRecord> {
+        self.coins.get(coin_id)
+    }
+
+    /// The coins which haven't been spent yet.
+    pub fn unspent_coins(&self) -> impl Iterator<Item = &Coin> {
+        self.coins
+            .values()
+            .filter(|record| !record.spent)
+            .map(|record| &record.coin)
+    }
+
+    /// Runs every spend in the bundle and checks their conditions against each other and
+    /// the coins being spent. If they are all valid, the spent coins are removed and the
+    /// created coins are added, otherwise nothing changes.
+    pub fn spend(&mut self, spends: &[CoinSpend]) -> Result<SpendOutcome, SimulatorError> {
+        let mut spent_ids = HashSet::new();
+        let mut records = Vec::new();
+        let mut conditions = Vec::new();
+        let mut cost = 0;
+
+        for spend in spends {
+            let coin_id = spend.coin.coin_id();
+            let record = *self
+                .coins
+                .get(&coin_id)
+                .ok_or(SimulatorError::UnknownCoin(coin_id))?;
+
+            if record.spent || !spent_ids.insert(coin_id) {
+                return Err(SimulatorError::DoubleSpend(coin_id));
+            }
+
+            let (output, spend_cost) = self.run_spend(spend, DEFAULT_MAX_COST - cost)?;
+            cost += spend_cost;
+            records.push(record);
+            conditions.push(output);
+        }
+
+        let announcements = announcements(spends, &conditions);
+        let mut created_coins = Vec::new();
+        let mut created_ids = HashSet::new();
+        let mut spent = 0;
+        let mut created = 0;
+        let mut reserved = 0;
+
+        for (record, conditions) in records.iter().zip(&conditions) {
+            spent += record.coin.amount as u128;
+
+            for condition in conditions {
+                if !self.check_assertion(record, condition, &announcements) {
+                    return Err(SimulatorError::FailedAssertion {
+                        coin_id: record.coin.coin_id(),
+                        condition: condition.clone(),
+                    });
+                }
+
+                match *condition {
+                    Condition::CreateCoin {
+                        puzzle_hash,
+                        amount,
+                    } => {
+                        let coin = Coin::new(record.coin.coin_id(), puzzle_hash, amount);
+                        let coin_id = coin.coin_id();
+                        if self.coins.contains_key(&coin_id) || !created_ids.insert(coin_id) {
+                            return Err(SimulatorError::DuplicateCoin(coin_id));
+                        }
+                        created += amount as u128;
+                        created_coins.push(coin);
+                    }
+                    Condition::ReserveFee(amount) => reserved += amount as u128,
+                    _ => {}
+                }
+            }
+        }
+
+        if created > spent {
+            return Err(SimulatorError::AmountNotConserved { spent, created });
+        }
+
+        let fee = spent - created;
+        if fee < reserved {
+            return Err(SimulatorError::InsufficientFee { fee, reserved });
+        }
+
+        for coin_id in spent_ids {
+            self.coins.get_mut(&coin_id).unwrap().spent = true;
+        }
+
+        for coin in &created_coins {
+            self.insert_coin(*coin);
+        }
+
+        Ok(SpendOutcome {
+            conditions,
+            created_coins,
+            fee,
+            cost,
+        })
+    }
+
+    fn insert_coin(&mut self, coin: Coin) {
+        self.coins.insert(
+            coin.coin_id(),
+            CoinRecord {
+                coin,
+                created_height: self.height,
+                created_timestamp: self.timestamp,
+                spent: false,
+            },
+        );
+    }
+
+    fn run_spend(
+        &self,
+        spend: &CoinSpend,
+        max_cost: u64,
+    ) -> Result<(Vec<Condition>, u64), SimulatorError> {
+        let mut allocator = Allocator::new();
+        let deserialize = |allocator: &mut Allocator, bytes: &[u8]| {
+            node_from_bytes(allocator, bytes)
+                .map_err(|error| SimulatorError::Deserialize(error.to_string()))
+        };
+
+        let puzzle = deserialize(&mut allocator, &spend.puzzle_reveal)?;
+        let solution = deserialize(&mut allocator, &spend.solution)?;
+
+        if tree_hash(&allocator, puzzle) != spend.coin.puzzle_hash {
+            return Err(SimulatorError::WrongPuzzle(spend.coin.coin_id()));
+        }
+
+        let (output, cost) = run(&mut allocator, puzzle, solution, max_cost)?;
+        Ok((parse_conditions(&allocator, output)?, cost))
+    }
+
+    fn check_assertion(
+        &self,
+        record: &CoinRecord,
+        condition: &Condition,
+        announcements: &HashSet<Bytes32>,
+    ) -> bool {
+        let coin = &record.coin;
+        let height = self.height as u64;
+        let created_height = record.created_height as u64;
+
+        match condition {
+            Condition::AssertMyCoinId(coin_id) => *coin_id == coin.coin_id(),
+            Condition::AssertMyParentId(parent_id) => *parent_id == coin.parent_coin_info,
+            Condition::AssertMyPuzzleHash(puzzle_hash) => *puzzle_hash == coin.puzzle_hash,
+            Condition::AssertMyAmount(amount) => *amount == coin.amount,
+            Condition::AssertCoinAnnouncement(id) | Condition::AssertPuzzleAnnouncement(id) => {
+                announcements.contains(id)
+            }
+            Condition::AssertSecondsRelative(seconds) => {
+                self.timestamp >= record.created_timestamp.saturating_add(*seconds)
+            }
+            Condition::AssertSecondsAbsolute(seconds) => self.timestamp >= *seconds,
+            Condition::AssertHeightRelative(blocks) => {
+                height >= created_height.saturating_add(*blocks)
+            }
+            Condition::AssertHeightAbsolute(blocks) => height >= *blocks,
+            _ => true,
+        }
+    }
+}
+
+/// The ids of every announcement made in the bundle. Coin announcements are prefixed
+/// with the id of the coin, and puzzle announcements with its puzzle hash.
+fn announcements(spends: &[CoinSpend], conditions: &[Vec<Condition>]) -> HashSet<Bytes32> {
+    let mut ids = HashSet::new();
+
+    for (spend, conditions) in spends.iter().zip(conditions) {
+        for condition in conditions {
+            let (prefix, message) = match condition {
+                Condition::CreateCoinAnnouncement(message) => (spend.coin.coin_id(), message),
+                Condition::CreatePuzzleAnnouncement(message) => (spend.coin.puzzle_hash, message),
+                _ => continue,
+            };
+            ids.insert(announcement_id(&prefix, message));
+        }
+    }
+
+    ids
+}
+
+/// The id that an announcement is asserted by.
+pub fn announcement_id(prefix: &Bytes32, message: &[u8]) -> Bytes32 {
+    let mut hasher = Sha256::new();
+    hasher.update(prefix);
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use clvmr::serde::node_to_bytes;
+    use rowan::ast::AstNode;
+    use rue_ast::Program;
+    use rue_compiler::Compiler;
+    use rue_lir::{OptLevel, PassManager};
+    use rue_parser::parse_text;
+
+    use super::*;
+
+    /// Compiles a puzzle, and returns it along with its puzzle hash.
+    fn compile(source: &str) -> (Vec<u8>, TreeHash) {
+        let (errors, node) = parse_text(source);
+        assert_eq!(errors, Vec::new());
+
+        let output = rue_hir::lower(Program::cast(node).unwrap());
+        assert_eq!(output.errors, Vec::new());
+
+        let passes = PassManager::new(OptLevel::O2);
+        let lir = rue_lir::lower(output.db, output.scope, &passes).unwrap();
+        let bytes = Compiler::new().compile_to_bytes(passes.run(lir)).unwrap();
+
+        let mut allocator = Allocator::new();
+        let puzzle = node_from_bytes(&mut allocator, &bytes).unwrap();
+        let puzzle_hash = tree_hash(&allocator, puzzle);
+        (bytes, puzzle_hash)
+    }
+
+    enum Arg<'a> {
+        Int(u64),
+        Bytes(&'a [u8]),
+    }
+
+    fn solution(args: &[Arg]) -> Vec<u8> {
+        let mut allocator = Allocator::new();
+        let mut list = allocator.null();
+        for arg in args.iter().rev() {
+            let value = match arg {
+                Arg::Int(value) => allocator.new_number((*value).into()).unwrap(),
+                Arg::Bytes(value) => allocator.new_atom(value).unwrap(),
+            };
+            list = allocator.new_pair(value, list).unwrap();
+        }
+        node_to_bytes(&allocator, list).unwrap()
+    }
+
+    const PAY: &str = r#"
+        fn main(puzzle_hash: String, amount: Int, fee: Int) -> [Condition] {
+            [create_coin(puzzle_hash, amount), reserve_fee(fee)]
+        }
+    "#;
+
+    #[test]
+    fn spend_creates_coins() {
+        let (puzzle, puzzle_hash) = compile(PAY);
+        let mut sim = Simulator::new();
+        let coin = sim.new_coin(puzzle_hash, 100);
+
+        let solution = solution(&[Arg::Bytes(&[7; 32]), Arg::Int(90), Arg::Int(10)]);
+        let outcome = sim
+            .spend(&[CoinSpend::new(coin, puzzle.clone(), solution.clone())])
+            .unwrap();
+
+        let child = Coin::new(coin.coin_id(), [7; 32], 90);
+        assert_eq!(outcome.created_coins, vec![child]);
+        assert_eq!(outcome.fee, 10);
+        assert!(outcome.cost > 0);
+        assert_eq!(sim.unspent_coins().collect::<Vec<_>>(), vec![&child]);
+
+        assert_eq!(
+            sim.spend(&[CoinSpend::new(coin, puzzle, solution)]),
+            Err(SimulatorError::DoubleSpend(coin.coin_id()))
+        );
+    }
+
+    #[test]
+    fn amounts_are_conserved() {
+        let (puzzle, puzzle_hash) = compile(PAY);
+        let mut sim = Simulator::new();
+        let coin = sim.new_coin(puzzle_hash, 100);
+
+        let overspend = solution(&[Arg::Bytes(&[7; 32]), Arg::Int(101), Arg::Int(0)]);
+        assert_eq!(
+            sim.spend(&[CoinSpend::new(coin, puzzle.clone(), overspend)]),
+            Err(SimulatorError::AmountNotConserved {
+                spent: 100,
+                created: 101
+            })
+        );
+
+        let overpaid_fee = solution(&[Arg::Bytes(&[7; 32]), Arg::Int(95), Arg::Int(10)]);
+        assert_eq!(
+            sim.spend(&[CoinSpend::new(coin, puzzle, overpaid_fee)]),
+            Err(SimulatorError::InsufficientFee {
+                fee: 5,
+                reserved: 10
+            })
+        );
+
+        assert!(sim.coin_record(&coin.coin_id()).is_some_and(|r| !r.spent));
+    }
+
+    #[test]
+    fn spends_must_reveal_the_right_puzzle() {
+        let (puzzle, _) = compile(PAY);
+        let mut sim = Simulator::new();
+        let coin = sim.new_coin([0; 32], 100);
+
+        assert_eq!(
+            sim.spend(&[CoinSpend::new(coin, puzzle.clone(), solution(&[]))]),
+            Err(SimulatorError::WrongPuzzle(coin.coin_id()))
+        );
+
+        let unknown = Coin::new([1; 32], [0; 32], 100);
+        assert_eq!(
+            sim.spend(&[CoinSpend::new(unknown, puzzle, solution(&[]))]),
+            Err(SimulatorError::UnknownCoin(unknown.coin_id()))
+        );
+    }
+
+    #[test]
+    fn assert_my_conditions() {
+        let (puzzle, puzzle_hash) = compile(
+            r#"
+            fn main(coin_id: String, parent_id: String, puzzle_hash: String, amount: Int) -> [Condition] {
+                [
+                    assert_my_coin_id(coin_id),
+                    assert_my_parent_id(parent_id),
+                    assert_my_puzzle_hash(puzzle_hash),
+                    assert_my_amount(amount)
+                ]
+            }
+            "#,
+        );
+        let mut sim = Simulator::new();
+        let coin = sim.new_coin(puzzle_hash, 100);
+        let coin_id = coin.coin_id();
+
+        let spend = |amount: u64| {
+            let solution = solution(&[
+                Arg::Bytes(&coin_id),
+                Arg::Bytes(&coin.parent_coin_info),
+                Arg::Bytes(&puzzle_hash),
+                Arg::Int(amount),
+            ]);
+            CoinSpend::new(coin, puzzle.clone(), solution)
+        };
+
+        assert_eq!(
+            sim.spend(&[spend(99)]),
+            Err(SimulatorError::FailedAssertion {
+                coin_id,
+                condition: Condition::AssertMyAmount(99)
+            })
+        );
+        assert_eq!(sim.spend(&[spend(100)]).unwrap().fee, 100);
+    }
+
+    #[test]
+    fn announcements_must_be_made_in_the_same_bundle() {
+        let (announcer, announcer_hash) = compile(
+            r#"
+            fn main(message: String) -> [Condition] {
+                [create_puzzle_announcement(message)]
+            }
+            "#,
+        );
+        let (asserter, asserter_hash) = compile(
+            r#"
+            fn main(announcement_id: String) -> [Condition] {
+                [assert_puzzle_announcement(announcement_id)]
+            }
+            "#,
+        );
+
+        let mut sim = Simulator::new();
+        let first = sim.new_coin(announcer_hash, 1);
+        let second = sim.new_coin(asserter_hash, 1);
+
+        let id = announcement_id(&announcer_hash, b"hello");
+        let announce = CoinSpend::new(first, announcer, solution(&[Arg::Bytes(b"hello")]));
+        let assert = CoinSpend::new(second, asserter, solution(&[Arg::Bytes(&id)]));
+
+        assert_eq!(
+            sim.spend(std::slice::from_ref(&assert)),
+            Err(SimulatorError::FailedAssertion {
+                coin_id: second.coin_id(),
+                condition: Condition::AssertPuzzleAnnouncement(id)
+            })
+        );
+        assert_eq!(sim.spend(&[announce, assert]).unwrap().fee, 2);
+    }
+
+    #[test]
+    fn relative_height_and_time() {
+        let (puzzle, puzzle_hash) = compile(
+            r#"
+            fn main(blocks: Int, seconds: Int) -> [Condition] {
+                [assert_height_relative(blocks), assert_seconds_relative(seconds)]
+            }
+            "#,
+        );
+        let mut sim = Simulator::new();
+        sim.pass_blocks(5);
+        let coin = sim.new_coin(puzzle_hash, 1);
+        let spend = CoinSpend::new(coin, puzzle, solution(&[Arg::Int(10), Arg::Int(60)]));
+
+        sim.pass_blocks(10);
+        assert!(matches!(
+            sim.spend(std::slice::from_ref(&spend)),
+            Err(SimulatorError::FailedAssertion {
+                condition: Condition::AssertSecondsRelative(60),
+                ..
+            })
+        ));
+
+        sim.pass_time(60);
+        assert!(sim.spend(&[spend]).is_ok());
+    }
+}
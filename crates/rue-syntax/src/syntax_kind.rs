@@ -19,6 +19,8 @@ pub enum SyntaxKind {
     String,
 
     Fn,
+    Inline,
+    Curry,
     If,
     Else,
     Return,
@@ -40,6 +42,7 @@ pub enum SyntaxKind {
     LessThan,
     Equals,
 
+    Pound,
     Dot,
     Comma,
     Colon,
@@ -52,13 +55,17 @@ pub enum SyntaxKind {
     BinaryExpr,
     CallExpr,
     IfExpr,
+    ListExpr,
 
     LetStmt,
 
     FnItem,
+    Attribute,
     FnParamList,
     FnParam,
 
+    ListType,
+
     Block,
     Program,
 }
@@ -87,6 +94,8 @@ impl fmt::Display for SyntaxKind {
             Self::String => write!(f, "string"),
 
             Self::Fn => write!(f, "`fn`"),
+            Self::Inline => write!(f, "`inline`"),
+            Self::Curry => write!(f, "`curry`"),
             Self::If => write!(f, "`if`"),
             Self::Else => write!(f, "`else`"),
             Self::Return => write!(f, "`return`"),
@@ -108,6 +117,7 @@ impl fmt::Display for SyntaxKind {
             Self::LessThan => write!(f, "`<`"),
             Self::Equals => write!(f, "`=`"),
 
+            Self::Pound => write!(f, "`#`"),
             Self::Dot => write!(f, "`.`"),
             Self::Comma => write!(f, "`,`"),
             Self::Colon => write!(f, "`:`"),
@@ -120,13 +130,17 @@ impl fmt::Display for SyntaxKind {
             Self::BinaryExpr => write!(f, "binary expression"),
             Self::CallExpr => write!(f, "call expression"),
             Self::IfExpr => write!(f, "`if` expression"),
+            Self::ListExpr => write!(f, "list expression"),
 
             Self::LetStmt => write!(f, "`let` statement"),
 
             Self::FnItem => write!(f, "`fn` item"),
+            Self::Attribute => write!(f, "attribute"),
             Self::FnParamList => write!(f, "parameter list"),
             Self::FnParam => write!(f, "parameter"),
 
+            Self::ListType => write!(f, "list type"),
+
             Self::Program => write!(f, "program"),
             Self::Block => write!(f, "block"),
         }
@@ -136,6 +150,8 @@ impl fmt::Display for SyntaxKind {
 #[macro_export]
 macro_rules! T {
     [fn] => { SyntaxKind::Fn };
+    [inline] => { SyntaxKind::Inline };
+    [curry] => { SyntaxKind::Curry };
     [if] => { SyntaxKind::If };
     [else] => { SyntaxKind::Else };
     [return] => { SyntaxKind::Return };
@@ -153,6 +169,7 @@ macro_rules! T {
     [>] => { SyntaxKind::GreaterThan };
     [<] => { SyntaxKind::LessThan };
     [=] => { SyntaxKind::Equals };
+    [#] => { SyntaxKind::Pound };
     [.] => { SyntaxKind::Dot };
     [,] => { SyntaxKind::Comma };
     [:] => { SyntaxKind::Colon };
@@ -1,59 +1,1160 @@
-use std::fs;
+use std::{
+    env, error, fmt, fs,
+    io::{self, IsTerminal, Read, Write},
+    iter,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use anyhow::anyhow;
-use clap::Parser;
-use rowan::ast::AstNode;
-use rue_ast::Program;
-use rue_compiler::Compiler;
-use rue_error::Error;
-use rue_parser::parse_text;
+use anyhow::{anyhow, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use clvmr::{
+    serde::{node_from_bytes, node_from_bytes_backrefs, node_to_bytes},
+    Allocator,
+};
+use rue_compiler::{
+    assemble, curry, disassemble, disassemble_value, parse_hex, run_traced, tree_hash, SourceMap,
+    DEFAULT_MAX_COST,
+};
+use rue_driver::{CompileError, CompileOptions, CompileOutput, CompiledProgram};
+use rue_error::{Code, Diagnostic, LineIndex, Renderer, Severity, TextRange};
+use rue_lir::{OptLevel, Pass, PassManager};
+use serde_json::json;
+
+use project::{Project, MANIFEST_NAME};
+
+mod project;
 
 /// Rue compiler.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// The file to compile.
-    input_file: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Checks files for errors and warnings, without compiling them. Without any files,
+    /// the source of the project's `Rue.toml` is checked instead.
+    Check {
+        /// The files to check, or `-` to read from stdin.
+        #[arg(value_name = "FILE")]
+        files: Vec<String>,
+
+        /// The project manifest to check, instead of the `Rue.toml` in the current directory.
+        #[arg(long, value_name = "PATH", conflicts_with = "files")]
+        manifest_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        diagnostics: DiagnosticArgs,
+    },
+    /// Compiles files into CLVM programs. Without any files, every target in the
+    /// project's `Rue.toml` is compiled instead.
+    Build {
+        /// The files to compile, or `-` to read from stdin.
+        #[arg(value_name = "FILE")]
+        files: Vec<String>,
+
+        /// The project manifest to build, instead of the `Rue.toml` in the current directory.
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["files", "output", "source_map", "emit", "hash"])]
+        manifest_path: Option<PathBuf>,
+
+        #[command(flatten)]
+        compile: CompileArgs,
+
+        /// The format of the output.
+        #[arg(long, value_enum, default_value_t = Emit::Hex)]
+        emit: Emit,
+
+        /// Serializes the program with back-references to repeated subtrees, and reports
+        /// how many bytes that saves.
+        #[arg(long)]
+        backrefs: bool,
+
+        /// Prints the sha256 tree hash of the program instead of the program itself.
+        #[arg(long, conflicts_with_all = ["emit", "backrefs"])]
+        hash: bool,
+
+        /// Writes the output to a file instead of stdout. Given once for each input file,
+        /// in the same order.
+        #[arg(short, long, value_name = "FILE")]
+        output: Vec<PathBuf>,
+
+        /// Writes a source map to a file, which maps each node path in the program
+        /// to the range in the source file that it was compiled from. Only one input
+        /// file can be compiled with this.
+        #[arg(long, value_name = "FILE")]
+        source_map: Option<PathBuf>,
+    },
+    /// Compiles a file and runs it with a solution.
+    Run {
+        /// The file to run, or `-` to read from stdin.
+        file: String,
+
+        #[command(flatten)]
+        compile: CompileArgs,
+
+        /// The solution in CLVM text format, such as `(42 "hello")`.
+        #[arg(long, conflicts_with = "solution_hex")]
+        solution: Option<String>,
+
+        /// The solution as serialized CLVM, encoded as hex.
+        #[arg(long)]
+        solution_hex: Option<String>,
+
+        /// The maximum cost that the program can use.
+        #[arg(long, default_value_t = DEFAULT_MAX_COST)]
+        max_cost: u64,
+    },
+    /// Compiles a file and curries values into it, for each of the curried parameters of `main`.
+    Curry {
+        /// The file to compile, or `-` to read from stdin.
+        file: String,
+
+        #[command(flatten)]
+        compile: CompileArgs,
+
+        /// The values to curry in, each in CLVM text format.
+        #[arg(value_name = "VALUE")]
+        values: Vec<String>,
+
+        /// Prints the curried program in CLVM text format, rather than hex.
+        #[arg(long)]
+        text: bool,
+    },
+    /// Compiles and runs each function marked with `#[test]`.
+    Test {
+        /// The files to test, or `-` to read from stdin.
+        #[arg(required = true, value_name = "FILE")]
+        files: Vec<String>,
+
+        #[command(flatten)]
+        compile: CompileArgs,
+
+        /// Only runs the tests whose name contains this text.
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// The maximum cost that each test can use.
+        #[arg(long, default_value_t = DEFAULT_MAX_COST)]
+        max_cost: u64,
+    },
+    /// Formats files in place, or prints the formatted source if it's read from stdin.
+    Fmt {
+        /// The files to format, or `-` to read from stdin.
+        #[arg(required = true, value_name = "FILE")]
+        files: Vec<String>,
+
+        /// Lists the files that aren't formatted instead of changing them, and fails
+        /// if there are any.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Prints a detailed explanation of a diagnostic code, such as `E0106`.
+    Explain {
+        /// The code to explain.
+        code: String,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct CompileArgs {
+    /// The optimization level, which is one of 0, 1, 2, or s. Defaults to 2, unless
+    /// the project's `Rue.toml` sets it.
+    #[arg(short = 'O')]
+    opt_level: Option<OptLevel>,
+
+    /// Enables a pass in addition to those selected by the optimization level.
+    #[arg(long, value_name = "PASS")]
+    enable_pass: Vec<Pass>,
+
+    /// Disables a pass that would otherwise be selected by the optimization level.
+    #[arg(long, value_name = "PASS")]
+    disable_pass: Vec<Pass>,
+
+    #[command(flatten)]
+    diagnostics: DiagnosticArgs,
+}
+
+impl CompileArgs {
+    fn options(&self) -> CompileOptions {
+        CompileOptions {
+            passes: self.passes(),
+            ..Default::default()
+        }
+    }
+
+    fn passes(&self) -> PassManager {
+        let mut passes = PassManager::new(self.opt_level.unwrap_or_default());
+        self.toggle_passes(&mut passes);
+        passes
+    }
+
+    /// Enables and disables the passes that were given, on top of those already selected.
+    fn toggle_passes(&self, passes: &mut PassManager) {
+        for pass in self.enable_pass.iter().copied() {
+            passes.enable(pass);
+        }
+        for pass in self.disable_pass.iter().copied() {
+            passes.disable(pass);
+        }
+    }
+
+    fn message_format(&self) -> MessageFormat {
+        self.diagnostics.message_format
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct DiagnosticArgs {
+    /// How diagnostics are written to stderr.
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    /// Rendered with the lines of source that they refer to.
+    Human,
+    /// One JSON object per line, for tools such as CI bots. Building programs also
+    /// writes a summary object at the end, with the path and hash of each artifact.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// The serialized program, encoded as hex.
+    Hex,
+    /// The serialized program, which must be written to a file.
+    Bin,
+    /// The program in CLVM text format.
+    Clvm,
+    /// The optimized low-level intermediate representation.
+    Lir,
+    /// The high-level intermediate representation of each function.
+    Hir,
+}
+
+/// A command failed for reasons that have already been reported, such as compiler
+/// diagnostics or failing tests, so there's nothing more to print.
+#[derive(Debug)]
+struct Failed;
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the command failed")
+    }
+}
+
+impl error::Error for Failed {}
+
+/// A source file, or the source read from stdin if its path is `-`.
+struct Input {
+    name: String,
+    path: Option<PathBuf>,
+    source: String,
+}
+
+impl Input {
+    fn read(path: &str) -> anyhow::Result<Self> {
+        if path == "-" {
+            let mut source = String::new();
+            io::stdin()
+                .read_to_string(&mut source)
+                .map_err(|error| anyhow!("could not read from stdin: {error}"))?;
+            return Ok(Self {
+                name: "<stdin>".to_string(),
+                path: None,
+                source,
+            });
+        }
+
+        let source = fs::read_to_string(path).map_err(|error| anyhow!("{path}: {error}"))?;
+        Ok(Self {
+            name: path.to_string(),
+            path: Some(PathBuf::from(path)),
+            source,
+        })
+    }
+
+    /// Reads each file up front, so a missing file is reported before anything is compiled.
+    fn read_all(paths: &[String]) -> anyhow::Result<Vec<Self>> {
+        if paths.iter().filter(|path| *path == "-").count() > 1 {
+            bail!("stdin (`-`) can only be read once");
+        }
+        paths.iter().map(|path| Self::read(path)).collect()
+    }
 }
 
-fn main() -> anyhow::Result<()> {
+/// Exits with 0 on success, 1 if the command failed, and 2 if the arguments are invalid.
+fn main() -> ExitCode {
     let args = Args::parse();
 
-    let source = fs::read_to_string(&args.input_file)
-        .map_err(|_| anyhow!("unknown file {}", args.input_file))?;
+    match execute(args.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            if !error.is::<Failed>() {
+                eprintln!("error: {error:#}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn execute(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Check {
+            files,
+            manifest_path,
+            diagnostics,
+        } if files.is_empty() => check_project(
+            manifest_path.as_deref().unwrap_or(Path::new(MANIFEST_NAME)),
+            diagnostics.message_format,
+        ),
+        Command::Check {
+            files,
+            manifest_path: _,
+            diagnostics,
+        } => check_files(&files, diagnostics.message_format),
+        Command::Build {
+            files,
+            manifest_path,
+            compile,
+            ..
+        } if files.is_empty() => build_project(
+            manifest_path.as_deref().unwrap_or(Path::new(MANIFEST_NAME)),
+            &compile,
+        ),
+        Command::Build {
+            files,
+            manifest_path: _,
+            compile,
+            emit,
+            backrefs,
+            hash,
+            output,
+            source_map,
+        } => build_files(
+            &files,
+            &compile,
+            emit,
+            backrefs,
+            hash,
+            &output,
+            source_map.as_deref(),
+        ),
+        Command::Run {
+            file,
+            compile,
+            solution,
+            solution_hex,
+            max_cost,
+        } => run_file(&file, &compile, solution, solution_hex, max_cost),
+        Command::Curry {
+            file,
+            compile,
+            values,
+            text,
+        } => curry_file(&file, &compile, &values, text),
+        Command::Test {
+            files,
+            compile,
+            filter,
+            max_cost,
+        } => test_files(&files, &compile, filter.as_deref(), max_cost),
+        Command::Fmt { files, check } => fmt_files(&files, check),
+        Command::Explain { code } => explain(&code),
+    }
+}
+
+/// Reports the diagnostics of each file, and fails if any of them have errors.
+fn check_files(files: &[String], message_format: MessageFormat) -> anyhow::Result<()> {
+    let options = CompileOptions {
+        check_only: true,
+        ..Default::default()
+    };
+    let mut failed = false;
 
-    match compile(&source) {
-        Ok(bytes) => println!("{}", hex::encode(&bytes)),
-        Err(errors) => eprintln!("{:?}", errors),
+    for input in Input::read_all(files)? {
+        match compile_input(&input, &options, message_format) {
+            Ok(output) => {
+                if let Some(error) = &output.error {
+                    report_compile_error(error, &input, message_format);
+                    failed = true;
+                }
+            }
+            Err(errors) => {
+                report_failure(&errors, &input, message_format);
+                failed = true;
+            }
+        }
     }
 
+    if failed {
+        return Err(Failed.into());
+    }
     Ok(())
 }
 
-fn compile(source: &str) -> Result<Vec<u8>, Vec<Error>> {
-    let mut errors = Vec::new();
+/// Checks the source of a project with the entry function of each of its targets, so that
+/// functions used by any of them aren't reported as unused.
+fn check_project(manifest_path: &Path, message_format: MessageFormat) -> anyhow::Result<()> {
+    if !manifest_path.exists() {
+        bail!(
+            "could not find {}, and no files were given to check",
+            manifest_path.display()
+        );
+    }
+
+    let project = Project::load(manifest_path)?;
+    let manifest = &project.manifest;
+    let passes = manifest.build.passes(None)?;
 
-    let (parser_errors, node) = parse_text(source);
-    errors.extend(parser_errors);
+    let input = Input::read(&project.source().to_string_lossy())?;
+    let entries = manifest
+        .targets
+        .iter()
+        .map(|target| target.entry.clone())
+        .collect::<Vec<_>>();
+
+    let mut failed = false;
+
+    for (index, target) in manifest.targets.iter().enumerate() {
+        let options = CompileOptions {
+            passes: passes.clone(),
+            entry: target.entry.clone(),
+            other_entries: entries.clone(),
+            check_only: true,
+            ..Default::default()
+        };
+
+        // Every target has the same diagnostics, so they're only reported once.
+        let output = if index == 0 {
+            match compile_input(&input, &options, message_format) {
+                Ok(output) => output,
+                Err(errors) => {
+                    report_failure(&errors, &input, message_format);
+                    return Err(Failed.into());
+                }
+            }
+        } else {
+            rue_driver::compile(&input.source, &options)
+        };
+
+        if let Some(error) = &output.error {
+            report_compile_error(error, &input, message_format);
+            if message_format == MessageFormat::Human {
+                eprintln!("error: could not check target `{}`", target.name);
+            }
+            failed = true;
+        }
+    }
+
+    if failed {
+        return Err(Failed.into());
+    }
+    Ok(())
+}
+
+fn build_files(
+    files: &[String],
+    compile: &CompileArgs,
+    emit: Emit,
+    backrefs: bool,
+    hash: bool,
+    output: &[PathBuf],
+    source_map: Option<&Path>,
+) -> anyhow::Result<()> {
+    if !output.is_empty() && output.len() != files.len() {
+        bail!(
+            "expected an output file for each of the {} input files, but was given {}",
+            files.len(),
+            output.len()
+        );
+    }
+
+    if emit == Emit::Bin && output.is_empty() {
+        bail!("`--emit bin` requires an output file, which can be set with `--output`");
+    }
 
-    let Some(program) = Program::cast(node) else {
-        return Err(errors);
+    if source_map.is_some() {
+        if matches!(emit, Emit::Lir | Emit::Hir) {
+            bail!("`--source-map` can only be used when emitting a CLVM program");
+        }
+        if files.len() > 1 {
+            bail!("`--source-map` can only be used when compiling a single file");
+        }
+    }
+
+    let inputs = Input::read_all(files)?;
+    let message_format = compile.message_format();
+    let options = CompileOptions {
+        backrefs,
+        emit_hir: emit == Emit::Hir,
+        emit_lir: emit == Emit::Lir,
+        ..compile.options()
     };
 
-    let rue_hir::Output {
-        errors: hir_errors,
-        db,
-        scope,
-    } = rue_hir::lower(program);
-    errors.extend(hir_errors);
+    let mut artifacts = Vec::new();
+    let mut failed = false;
+
+    for (index, input) in inputs.iter().enumerate() {
+        let output = output.get(index);
+
+        // The HIR is emitted without a program, since it can be written even if there is
+        // no entry point to compile.
+        let result = compile_input(input, &options, message_format).and_then(|output| {
+            if emit == Emit::Hir {
+                return Ok((output.hir, None));
+            }
+            let program = compiled_program(output, input, message_format)?;
+            Ok((None, Some(program)))
+        });
+
+        let (hir, program) = match result {
+            Ok(result) => result,
+            Err(errors) => {
+                report_failure(&errors, input, message_format);
+                failed = true;
+                continue;
+            }
+        };
+
+        if let (Some(path), Some(program)) = (source_map, &program) {
+            write_source_map(path, &program.source_map, &input.name);
+        }
+
+        // The tree hash is only known when a CLVM program is built, rather than the HIR.
+        let bytes = match &program {
+            None => hir.unwrap_or_default().into_bytes(),
+            Some(program) if hash => format!("{}\n", hex::encode(program.hash)).into_bytes(),
+            Some(program) => emit_program(program, emit)?,
+        };
+        let tree_hash = program.map(|program| program.hash);
+
+        match output {
+            Some(path) => fs::write(path, bytes)
+                .map_err(|error| anyhow!("could not write to {}: {error}", path.display()))?,
+            None => io::stdout().write_all(&bytes)?,
+        }
+
+        artifacts.push(json!({
+            "file": input.name,
+            "path": output.map(|path| path.display().to_string()),
+            "hash": tree_hash.map(hex::encode),
+        }));
+    }
+
+    if message_format == MessageFormat::Json {
+        let summary = json!({
+            "type": "summary",
+            "success": !failed,
+            "artifacts": artifacts,
+        });
+        eprintln!("{summary}");
+    }
+
+    if failed {
+        return Err(Failed.into());
+    }
+    Ok(())
+}
+
+/// Compiles every target of a project into its output directory, along with a
+/// `manifest.json` that lists the hash and size of each puzzle.
+fn build_project(manifest_path: &Path, compile: &CompileArgs) -> anyhow::Result<()> {
+    if !manifest_path.exists() {
+        bail!(
+            "could not find {}, and no files were given to build",
+            manifest_path.display()
+        );
+    }
+
+    let project = Project::load(manifest_path)?;
+    let manifest = &project.manifest;
+    let message_format = compile.message_format();
+
+    let mut passes = manifest.build.passes(compile.opt_level)?;
+    compile.toggle_passes(&mut passes);
+
+    let input = Input::read(&project.source().to_string_lossy())?;
+    let entries = manifest
+        .targets
+        .iter()
+        .map(|target| target.entry.clone())
+        .collect::<Vec<_>>();
+
+    let output_dir = project.output_dir();
+    let mut puzzles = Vec::new();
+    let mut failed = false;
+
+    for (index, target) in manifest.targets.iter().enumerate() {
+        let options = CompileOptions {
+            passes: passes.clone(),
+            entry: target.entry.clone(),
+            other_entries: entries.clone(),
+            backrefs: manifest.build.backrefs,
+            ..Default::default()
+        };
+
+        // Every target is compiled from the same source, so they all have the same
+        // diagnostics. They're only reported once, and no target is built if there are errors.
+        let output = if index == 0 {
+            match compile_input(&input, &options, message_format) {
+                Ok(output) => output,
+                Err(errors) => {
+                    report_failure(&errors, &input, message_format);
+                    if message_format == MessageFormat::Json {
+                        eprintln!("{}", json!({ "type": "summary", "success": false }));
+                    }
+                    return Err(Failed.into());
+                }
+            }
+        } else {
+            rue_driver::compile(&input.source, &options)
+        };
+
+        let Some(CompiledProgram {
+            bytes,
+            hash: tree_hash,
+            ..
+        }) = output.program
+        else {
+            if let Some(error) = &output.error {
+                report_compile_error(error, &input, message_format);
+            }
+            if message_format == MessageFormat::Human {
+                eprintln!("error: could not compile target `{}`", target.name);
+            }
+            failed = true;
+            continue;
+        };
+
+        let path = output_dir.join(target.output());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|error| anyhow!("could not create {}: {error}", parent.display()))?;
+        }
+        fs::write(&path, format!("{}\n", hex::encode(&bytes)))
+            .map_err(|error| anyhow!("could not write to {}: {error}", path.display()))?;
 
-    let Some(scope) = scope else {
-        return Err(errors);
+        if message_format == MessageFormat::Human {
+            eprintln!(
+                "compiled `{}` to {} ({} bytes)",
+                target.name,
+                path.display(),
+                bytes.len()
+            );
+        }
+
+        puzzles.push(json!({
+            "name": target.name,
+            "entry": target.entry,
+            "path": target.output().display().to_string(),
+            "hash": hex::encode(tree_hash),
+            "size": bytes.len(),
+        }));
+    }
+
+    // The manifest is only written when every target was built, so it never lists puzzles
+    // that are out of date.
+    if !failed {
+        let path = output_dir.join("manifest.json");
+        let build_manifest = json!({ "puzzles": puzzles });
+        fs::write(
+            &path,
+            format!("{}\n", serde_json::to_string_pretty(&build_manifest)?),
+        )
+        .map_err(|error| anyhow!("could not write to {}: {error}", path.display()))?;
+    }
+
+    if message_format == MessageFormat::Json {
+        let summary = json!({
+            "type": "summary",
+            "success": !failed,
+            "artifacts": puzzles,
+        });
+        eprintln!("{summary}");
+    }
+
+    if failed {
+        return Err(Failed.into());
+    }
+    Ok(())
+}
+
+fn run_file(
+    file: &str,
+    compile: &CompileArgs,
+    solution: Option<String>,
+    solution_hex: Option<String>,
+    max_cost: u64,
+) -> anyhow::Result<()> {
+    let input = Input::read(file)?;
+    let message_format = compile.message_format();
+
+    let CompiledProgram {
+        bytes, source_map, ..
+    } = match compile_input(&input, &compile.options(), message_format)
+        .and_then(|output| compiled_program(output, &input, message_format))
+    {
+        Ok(program) => program,
+        Err(errors) => {
+            report_failure(&errors, &input, message_format);
+            return Err(Failed.into());
+        }
     };
 
-    let Some(lir) = rue_lir::lower(db, scope) else {
-        return Err(errors);
+    let mut allocator = Allocator::new();
+    let program = node_from_bytes(&mut allocator, &bytes)?;
+
+    let solution = match (solution, solution_hex) {
+        (Some(text), _) => {
+            assemble(&mut allocator, &text).map_err(|error| anyhow!("invalid solution: {error}"))?
+        }
+        (None, Some(hex)) => {
+            let bytes = parse_hex(hex.trim_start_matches("0x")).ok_or_else(|| {
+                anyhow!("invalid solution: expected an even number of hex digits")
+            })?;
+            node_from_bytes(&mut allocator, &bytes)
+                .map_err(|error| anyhow!("invalid solution: {error}"))?
+        }
+        (None, None) => allocator.null(),
     };
 
-    Ok(Compiler::new().compile_to_bytes(lir))
+    let (value, cost) = run_traced(&mut allocator, program, solution, max_cost, &source_map)
+        .map_err(|error| anyhow!(located_error(&error.error, error.range, &input)))?;
+
+    println!("{}", disassemble_value(&allocator, value));
+    println!("cost: {cost}");
+
+    Ok(())
+}
+
+/// An error message, followed by the line of source that it happened on if it's known.
+fn located_error(message: &impl fmt::Display, range: Option<TextRange>, input: &Input) -> String {
+    match range {
+        Some(range) => format!(
+            "{message}\n{}",
+            Renderer::new(&input.name, &input.source)
+                .render_snippet(Severity::Error, range)
+                .trim_end()
+        ),
+        None => message.to_string(),
+    }
+}
+
+fn write_source_map(path: &Path, source_map: &SourceMap, input_file: &str) {
+    if let Err(error) = fs::write(path, source_map.to_text(input_file)) {
+        eprintln!("could not write source map to {}: {error}", path.display());
+    }
+}
+
+fn curry_file(
+    file: &str,
+    compile: &CompileArgs,
+    values: &[String],
+    text: bool,
+) -> anyhow::Result<()> {
+    let input = Input::read(file)?;
+    let message_format = compile.message_format();
+
+    let result = compile_input(&input, &compile.options(), message_format).and_then(|output| {
+        let parameters = output.curried_parameters.clone();
+        let program = compiled_program(output, &input, message_format)?;
+        Ok((parameters, program.bytes))
+    });
+
+    let (parameters, bytes) = match result {
+        Ok(result) => result,
+        Err(errors) => {
+            report_failure(&errors, &input, message_format);
+            return Err(Failed.into());
+        }
+    };
+
+    if values.len() != parameters.len() {
+        bail!(
+            "expected {} curried values for `main({})`, but was given {}",
+            parameters.len(),
+            parameters.join(", "),
+            values.len()
+        );
+    }
+
+    let mut allocator = Allocator::new();
+    let program = node_from_bytes(&mut allocator, &bytes)?;
+
+    let mut args = Vec::new();
+    for (value, parameter) in values.iter().zip(&parameters) {
+        let arg = assemble(&mut allocator, value)
+            .map_err(|error| anyhow!("invalid value for `{parameter}`: {error}"))?;
+        args.push(arg);
+    }
+
+    let curried = curry(&mut allocator, program, &args)?;
+
+    if text {
+        println!("{}", disassemble(&allocator, curried));
+    } else {
+        println!("{}", hex::encode(node_to_bytes(&allocator, curried)?));
+    }
+    println!("hash: {}", hex::encode(tree_hash(&allocator, curried)));
+
+    Ok(())
+}
+
+/// Runs the tests in each file, and fails if any of them fail or a file can't be compiled.
+fn test_files(
+    files: &[String],
+    compile: &CompileArgs,
+    filter: Option<&str>,
+    max_cost: u64,
+) -> anyhow::Result<()> {
+    let mut failed = false;
+
+    for (index, input) in Input::read_all(files)?.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        if !test_file(input, compile, filter, max_cost) {
+            failed = true;
+        }
+    }
+
+    if failed {
+        return Err(Failed.into());
+    }
+    Ok(())
+}
+
+/// Runs the tests in a file, and returns whether they all passed.
+fn test_file(input: &Input, compile: &CompileArgs, filter: Option<&str>, max_cost: u64) -> bool {
+    let options = compile.options();
+    let message_format = compile.message_format();
+
+    let output = match compile_input(input, &options, message_format) {
+        Ok(output) => output,
+        Err(errors) => {
+            report_failure(&errors, input, message_format);
+            return false;
+        }
+    };
+
+    let tests = output
+        .tests
+        .into_iter()
+        .filter(|(name, _)| filter.is_none_or(|filter| name.contains(filter)))
+        .collect::<Vec<_>>();
+
+    println!("running {} tests in {}", tests.len(), input.name);
+
+    let mut failures = Vec::new();
+
+    for (name, test) in tests.iter() {
+        // Each test is compiled into its own program, as if it was `main`.
+        let test_options = CompileOptions {
+            entry: name.clone(),
+            other_entries: vec!["main".to_string()],
+            ..options.clone()
+        };
+        let output = rue_driver::compile(&input.source, &test_options);
+
+        let result = output
+            .program
+            .ok_or_else(|| match &output.error {
+                Some(error) => located_error(&error.kind, error.range, input),
+                None => "the test could not be compiled".to_string(),
+            })
+            .and_then(|program| {
+                let mut allocator = Allocator::new();
+                let node = node_from_bytes(&mut allocator, &program.bytes)
+                    .map_err(|error| error.to_string())?;
+                let solution = allocator.null();
+                run_traced(
+                    &mut allocator,
+                    node,
+                    solution,
+                    max_cost,
+                    &program.source_map,
+                )
+                .map(|_| ())
+                .map_err(|error| located_error(&error.error, error.range, input))
+            });
+
+        let failure = match result {
+            Ok(()) if test.should_fail => Some("the test was expected to fail".to_string()),
+            Err(error) if !test.should_fail => Some(error),
+            _ => None,
+        };
+
+        match failure {
+            Some(failure) => {
+                println!("test {name} ... FAILED");
+                failures.push((name, failure));
+            }
+            None => println!("test {name} ... ok"),
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (name, failure) in &failures {
+            println!("    {name}: {}", failure.replace('\n', "\n    "));
+        }
+    }
+
+    let result = if failures.is_empty() { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {result}. {} passed; {} failed",
+        tests.len() - failures.len(),
+        failures.len()
+    );
+
+    failures.is_empty()
+}
+
+/// Rewrites each file in the standard style. Source read from stdin is printed instead.
+fn fmt_files(files: &[String], check: bool) -> anyhow::Result<()> {
+    let mut failed = false;
+
+    for input in Input::read_all(files)? {
+        let formatted = match rue_fmt::format(&input.source) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                report_failure(&errors, &input, MessageFormat::Human);
+                failed = true;
+                continue;
+            }
+        };
+
+        let changed = formatted != input.source;
+
+        match &input.path {
+            _ if check => {
+                if changed {
+                    println!("{} is not formatted", input.name);
+                    failed = true;
+                }
+            }
+            Some(path) => {
+                if changed {
+                    fs::write(path, formatted)
+                        .map_err(|error| anyhow!("could not write to {}: {error}", input.name))?;
+                }
+            }
+            None => print!("{formatted}"),
+        }
+    }
+
+    if failed {
+        return Err(Failed.into());
+    }
+    Ok(())
+}
+
+fn explain(code: &str) -> anyhow::Result<()> {
+    let Some(code) = Code::parse(&code.to_uppercase()) else {
+        bail!("unknown diagnostic code `{code}`");
+    };
+    print!("{}", code.explanation());
+    Ok(())
+}
+
+/// Compiles a file into a program, printing any warnings. The errors are returned if
+/// there were any.
+fn compile_input(
+    input: &Input,
+    options: &CompileOptions,
+    message_format: MessageFormat,
+) -> Result<CompileOutput, Vec<Diagnostic>> {
+    let output = rue_driver::compile(&input.source, options);
+
+    let warnings = output.warnings().cloned().collect::<Vec<_>>();
+    report(&warnings, input, message_format);
+
+    let errors = output.errors().cloned().collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(output)
+    } else {
+        Err(errors)
+    }
+}
+
+/// The program that was compiled, or an empty list of errors if it couldn't be, after
+/// reporting why.
+fn compiled_program(
+    output: CompileOutput,
+    input: &Input,
+    message_format: MessageFormat,
+) -> Result<CompiledProgram, Vec<Diagnostic>> {
+    if let Some(error) = &output.error {
+        report_compile_error(error, input, message_format);
+    }
+    output.program.ok_or_else(Vec::new)
+}
+
+/// Reports the errors that stopped a file from being compiled, followed by a summary
+/// of how many there were.
+fn report_failure(errors: &[Diagnostic], input: &Input, message_format: MessageFormat) {
+    report(errors, input, message_format);
+
+    if message_format == MessageFormat::Human {
+        match errors.len() {
+            0 => eprintln!("error: could not compile `{}`", input.name),
+            1 => eprintln!(
+                "error: could not compile `{}` due to a previous error",
+                input.name
+            ),
+            count => eprintln!(
+                "error: could not compile `{}` due to {count} previous errors",
+                input.name
+            ),
+        }
+        if let Some(error) = errors.first() {
+            eprintln!(
+                "For more information about an error, try `rue explain {}`.",
+                error.code()
+            );
+        }
+    }
+}
+
+/// Prints diagnostics with the source they refer to, in color if stderr is a terminal.
+fn report(diagnostics: &[Diagnostic], input: &Input, message_format: MessageFormat) {
+    if message_format == MessageFormat::Json {
+        let line_index = LineIndex::new(&input.source);
+        for diagnostic in diagnostics {
+            eprintln!(
+                "{}",
+                diagnostic_json(diagnostic, &input.name, &input.source, &line_index)
+            );
+        }
+        return;
+    }
+
+    let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let renderer = Renderer::new(&input.name, &input.source).with_color(color);
+    for diagnostic in diagnostics {
+        eprintln!("{}", renderer.render(diagnostic));
+    }
+}
+
+/// Prints an error from the backend, which is reported like a diagnostic without a code.
+fn report_compile_error(error: &CompileError, input: &Input, message_format: MessageFormat) {
+    if message_format == MessageFormat::Human {
+        eprintln!("error: {}", located_error(&error.kind, error.range, input));
+        return;
+    }
+
+    let line_index = LineIndex::new(&input.source);
+    let json = json!({
+        "type": "diagnostic",
+        "file": input.name,
+        "severity": Severity::Error.to_string(),
+        "code": null,
+        "message": error.kind.to_string(),
+        "span": error.range.map(|range| span_json(range, &input.source, &line_index)),
+        "labels": [],
+        "notes": [],
+        "rendered": format!("error: {}\n", located_error(&error.kind, error.range, input)),
+    });
+    eprintln!("{json}");
+}
+
+/// Converts a diagnostic to JSON. Lines and columns start at 1, and columns are counted
+/// in bytes. Ranges don't include the whitespace around them, unlike the syntax nodes
+/// that they come from.
+fn diagnostic_json(
+    diagnostic: &Diagnostic,
+    input_file: &str,
+    source: &str,
+    line_index: &LineIndex,
+) -> serde_json::Value {
+    let span = |range: TextRange| span_json(range, source, line_index);
+
+    let labels = iter::once((diagnostic.primary(), true))
+        .chain(diagnostic.secondary().iter().map(|label| (label, false)))
+        .map(|(label, is_primary)| {
+            json!({
+                "span": span(label.range),
+                "message": label.message,
+                "is_primary": is_primary,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let notes = diagnostic
+        .notes()
+        .iter()
+        .map(|note| {
+            let suggestion = note.suggestion.as_ref().map(|suggestion| {
+                json!({
+                    "span": span(suggestion.range),
+                    "replacement": suggestion.replacement,
+                })
+            });
+            json!({
+                "kind": note.kind.to_string(),
+                "message": note.message,
+                "suggestion": suggestion,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "type": "diagnostic",
+        "file": input_file,
+        "severity": diagnostic.severity().to_string(),
+        "code": diagnostic.code().as_str(),
+        "message": diagnostic.message(),
+        "span": span(diagnostic.range()),
+        "labels": labels,
+        "notes": notes,
+        "rendered": Renderer::new(input_file, source).render(diagnostic),
+    })
+}
+
+fn span_json(range: TextRange, source: &str, line_index: &LineIndex) -> serde_json::Value {
+    let range = range.trim_whitespace(source);
+    let start = line_index.line_col(range.from());
+    let end = line_index.line_col(range.to());
+    json!({
+        "start": range.from().pos(),
+        "end": range.to().pos(),
+        "line_start": start.line + 1,
+        "column_start": start.col + 1,
+        "line_end": end.line + 1,
+        "column_end": end.col + 1,
+    })
+}
+
+/// Writes a compiled program in the format that was asked for.
+fn emit_program(program: &CompiledProgram, emit: Emit) -> anyhow::Result<Vec<u8>> {
+    if let (Emit::Hex | Emit::Bin, Some(plain_size)) = (emit, program.size_without_backrefs) {
+        let compact_size = program.bytes.len();
+        eprintln!(
+            "{compact_size} bytes with back-references, {plain_size} bytes without ({} saved)",
+            plain_size as isize - compact_size as isize
+        );
+    }
+
+    Ok(match emit {
+        Emit::Hex => format!("{}\n", hex::encode(&program.bytes)).into_bytes(),
+        Emit::Bin => program.bytes.clone(),
+        Emit::Clvm => {
+            let mut allocator = Allocator::new();
+            let node = node_from_bytes_backrefs(&mut allocator, &program.bytes)?;
+            format!("{}\n", disassemble(&allocator, node)).into_bytes()
+        }
+        Emit::Lir => {
+            let lir = program
+                .lir
+                .as_ref()
+                .expect("the LIR is kept when it's emitted");
+            format!("{lir:#}\n").into_bytes()
+        }
+        Emit::Hir => unreachable!("the HIR is emitted before lowering"),
+    })
 }
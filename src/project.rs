@@ -0,0 +1,265 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use rue_lir::{OptLevel, Pass, PassManager};
+use serde::Deserialize;
+
+/// The name of the file that describes a project.
+pub const MANIFEST_NAME: &str = "Rue.toml";
+
+/// A project's `Rue.toml`, which lists the puzzles that are compiled from its source.
+///
+/// Rue doesn't have modules yet, so a project is a single source file. Every target's
+/// entry function and the helpers they share have to be defined in it.
+///
+/// ```toml
+/// source = "src/puzzles.rue"
+/// output = "build"
+///
+/// [build]
+/// opt-level = "s"
+///
+/// [[target]]
+/// name = "p2_delegated"
+/// entry = "delegated"
+/// output = "p2_delegated.clvm.hex"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The file with the entry function of every target, and the helpers that they share.
+    #[serde(default = "default_source")]
+    pub source: PathBuf,
+    /// The directory that targets and the build manifest are written to.
+    #[serde(default = "default_output")]
+    pub output: PathBuf,
+    #[serde(default)]
+    pub build: BuildOptions,
+    #[serde(rename = "target", default)]
+    pub targets: Vec<Target>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BuildOptions {
+    /// The optimization level, which is one of 0, 1, 2, or s.
+    pub opt_level: Option<OptLevelValue>,
+    pub enable_passes: Vec<String>,
+    pub disable_passes: Vec<String>,
+    /// Serializes each puzzle with back-references to repeated subtrees.
+    pub backrefs: bool,
+}
+
+/// Optimization levels can be written as numbers, like they are in `Cargo.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum OptLevelValue {
+    Number(u8),
+    Text(String),
+}
+
+/// A puzzle that is compiled from one of the functions in the source.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    pub name: String,
+    /// The function that the puzzle runs, as if it was `main`.
+    #[serde(default = "default_entry")]
+    pub entry: String,
+    /// Where the serialized puzzle is written as hex, relative to the output directory.
+    /// Defaults to the name of the target with a `.hex` extension.
+    pub output: Option<PathBuf>,
+}
+
+fn default_source() -> PathBuf {
+    PathBuf::from("src/main.rue")
+}
+
+fn default_output() -> PathBuf {
+    PathBuf::from("build")
+}
+
+fn default_entry() -> String {
+    "main".to_string()
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let manifest: Self = toml::from_str(text)?;
+
+        if manifest.targets.is_empty() {
+            bail!("there are no targets, which can be added with `[[target]]`");
+        }
+
+        let mut names = HashSet::new();
+        for target in &manifest.targets {
+            if target.name.is_empty() {
+                bail!("targets must have a name");
+            }
+            if !names.insert(&target.name) {
+                bail!("there is already a target named `{}`", target.name);
+            }
+
+            // Targets are written inside the output directory, and nowhere else.
+            let output = target.output();
+            if output
+                .components()
+                .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                bail!(
+                    "the output of target `{}` must be a relative path inside the output \
+                    directory, but it is `{}`",
+                    target.name,
+                    output.display()
+                );
+            }
+        }
+
+        manifest.build.passes(None)?;
+
+        Ok(manifest)
+    }
+}
+
+impl BuildOptions {
+    pub fn opt_level(&self) -> anyhow::Result<Option<OptLevel>> {
+        let text = match &self.opt_level {
+            None => return Ok(None),
+            Some(OptLevelValue::Number(number)) => number.to_string(),
+            Some(OptLevelValue::Text(text)) => text.clone(),
+        };
+        text.parse::<OptLevel>()
+            .map(Some)
+            .map_err(|error| anyhow!(error))
+    }
+
+    /// The passes selected by the options. An optimization level that is given, such as
+    /// one from the command line, is used instead of the manifest's.
+    pub fn passes(&self, opt_level: Option<OptLevel>) -> anyhow::Result<PassManager> {
+        let opt_level = match opt_level {
+            Some(opt_level) => opt_level,
+            None => self.opt_level()?.unwrap_or_default(),
+        };
+        let mut passes = PassManager::new(opt_level);
+        for name in &self.enable_passes {
+            passes.enable(name.parse::<Pass>().map_err(|error| anyhow!(error))?);
+        }
+        for name in &self.disable_passes {
+            passes.disable(name.parse::<Pass>().map_err(|error| anyhow!(error))?);
+        }
+        Ok(passes)
+    }
+}
+
+impl Target {
+    pub fn output(&self) -> PathBuf {
+        self.output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.hex", self.name)))
+    }
+}
+
+/// A manifest, and the directory that the paths in it are relative to.
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub root: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Project {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| anyhow!("could not read {}: {error}", path.display()))?;
+        let manifest = Manifest::parse(&text)
+            .map_err(|error| anyhow!("invalid manifest {}: {error}", path.display()))?;
+        let root = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        Ok(Self { root, manifest })
+    }
+
+    pub fn source(&self) -> PathBuf {
+        self.root.join(&self.manifest.source)
+    }
+
+    pub fn output_dir(&self) -> PathBuf {
+        self.root.join(&self.manifest.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest() {
+        let manifest = Manifest::parse(
+            r#"
+            [build]
+            opt-level = 1
+            disable-passes = ["inline"]
+
+            [[target]]
+            name = "standard"
+
+            [[target]]
+            name = "delegated"
+            entry = "p2_delegated"
+            output = "puzzles/delegated.clvm.hex"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.source, PathBuf::from("src/main.rue"));
+        assert_eq!(manifest.output, PathBuf::from("build"));
+        assert_eq!(manifest.build.opt_level().unwrap(), Some(OptLevel::O1));
+        assert_eq!(manifest.targets[0].entry, "main");
+        assert_eq!(manifest.targets[0].output(), PathBuf::from("standard.hex"));
+        assert_eq!(
+            manifest.targets[1].output(),
+            PathBuf::from("puzzles/delegated.clvm.hex")
+        );
+    }
+
+    #[test]
+    fn invalid_manifests() {
+        let cases = [
+            ("source = \"main.rue\"", "there are no targets"),
+            (
+                "[[target]]\nname = \"a\"\n[[target]]\nname = \"a\"",
+                "already a target named `a`",
+            ),
+            (
+                "[build]\nopt-level = 3\n[[target]]\nname = \"a\"",
+                "unknown optimization level `3`",
+            ),
+            (
+                "[build]\nenable-passes = [\"fast\"]\n[[target]]\nname = \"a\"",
+                "unknown pass `fast`",
+            ),
+            (
+                "[[target]]\nname = \"a\"\nentyr = \"b\"",
+                "unknown field `entyr`",
+            ),
+            (
+                "[[target]]\nname = \"a\"\noutput = \"../a.hex\"",
+                "output of target `a` must be a relative path",
+            ),
+            (
+                "[[target]]\nname = \"a\"\noutput = \"/tmp/a.hex\"",
+                "output of target `a` must be a relative path",
+            ),
+            (
+                "[[target]]\nname = \"../a\"",
+                "output of target `../a` must be a relative path",
+            ),
+        ];
+
+        for (text, expected) in cases {
+            let error = Manifest::parse(text).unwrap_err().to_string();
+            assert!(error.contains(expected), "{error:?} for {text:?}");
+        }
+    }
+}